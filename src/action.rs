@@ -0,0 +1,110 @@
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+use serde_yml as yml;
+
+use crate::{context::ContextDef, input::BindsErr};
+
+const ACTIONS_PATH: &str = "actions.yaml";
+
+/// Handle to an action in the [`ActionManifest`], looked up by name with
+/// [`Input::action_id`](crate::input::Input::action_id).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ActionId(u16);
+impl ActionId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionKind {
+    Button,
+    Axis1d,
+    Axis2d,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionDef {
+    pub name: String,
+    pub kind: ActionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ActionValue {
+    Pressed,
+    Released,
+    Axis1d(f32),
+    Axis2d(f32, f32),
+}
+
+/// An input event for a named action, sent to the game and given to the first matching
+/// [`ActionHandler`](crate::game::ActionHandler).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Action {
+    pub id: ActionId,
+    pub value: ActionValue,
+}
+
+/// Every action the game can bind inputs to and the input contexts that use them, loaded from
+/// `actions.yaml`. Bindings refer to actions by name, and the order of the manifest determines
+/// each action's [`ActionId`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionManifest {
+    actions: Vec<ActionDef>,
+    contexts: Vec<ContextDef>,
+}
+impl ActionManifest {
+    pub fn load() -> Result<Self, BindsErr> {
+        let file = File::open(ACTIONS_PATH)?;
+        Ok(yml::from_reader(file)?)
+    }
+    pub fn id(&self, name: &str) -> Option<ActionId> {
+        self.actions
+            .iter()
+            .position(|def| def.name == name)
+            .map(|i| ActionId(i as u16))
+    }
+    pub fn get(&self, id: ActionId) -> &ActionDef {
+        &self.actions[id.index()]
+    }
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (ActionId, &ActionDef)> {
+        self.actions
+            .iter()
+            .enumerate()
+            .map(|(i, def)| (ActionId(i as u16), def))
+    }
+    pub fn contexts(&self) -> &[ContextDef] {
+        &self.contexts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_manifest_has_engine_actions() {
+        let manifest: ActionManifest = yml::from_str(include_str!("../actions.yaml")).unwrap();
+        for (name, kind) in [
+            ("Pause", ActionKind::Button),
+            ("Step", ActionKind::Button),
+            ("Move", ActionKind::Axis2d),
+            ("Look", ActionKind::Axis2d),
+            ("ToggleInspector", ActionKind::Button),
+            ("ToggleProfiler", ActionKind::Button),
+            ("ToggleConsole", ActionKind::Button),
+            ("ToggleSettings", ActionKind::Button),
+            ("ToggleCursor", ActionKind::Button),
+        ] {
+            let id = manifest
+                .id(name)
+                .unwrap_or_else(|| panic!("{name} is missing"));
+            assert_eq!(manifest.get(id).kind, kind, "{name}");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use crate::{action::ActionId, clock::GameTime, input::Input};
+
+/// Presses closer together than this count as a double tap
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+/// Presses and releases of a button action since the last game step.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ButtonTransitions {
+    pub presses: u16,
+    pub releases: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ButtonState {
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+    double_tapped: bool,
+    /// Input time of the latest press
+    pressed_at: Option<Duration>,
+}
+
+/// State of every button action as of the current game step, stored as a resource and updated by
+/// the game loop before every step. Unlike queued actions, this is the same for every process
+/// within a step, and presses that start and end between steps still show up as just pressed and
+/// just released. Actions blocked by the input contexts read as released.
+#[derive(Debug, Default)]
+pub struct ButtonStates {
+    states: Vec<ButtonState>,
+    /// Input time of the current step. This is real time, so double taps and hold durations don't
+    /// change with the timescale or how many steps run per game loop
+    now: Duration,
+}
+impl ButtonStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Takes the button transitions from `input` for the step `time` was advanced to.
+    pub fn update(&mut self, time: &GameTime, input: &mut Input) {
+        self.now = time.real_elapsed;
+        let now = self.now;
+        let transitions = input.take_button_transitions();
+        self.states
+            .resize(transitions.len(), ButtonState::default());
+        for ((id, _), (state, transitions)) in
+            input.actions().zip(self.states.iter_mut().zip(transitions))
+        {
+            if input.contexts().claimed_by(id).is_none() {
+                *state = ButtonState::default();
+                continue;
+            }
+            state.pressed = input.is_held(id);
+            state.just_pressed = transitions.presses > 0;
+            state.just_released = transitions.releases > 0;
+            state.double_tapped = transitions.presses > 1
+                || (state.just_pressed
+                    && state
+                        .pressed_at
+                        .is_some_and(|at| now.saturating_sub(at) <= DOUBLE_TAP_WINDOW));
+            if state.just_pressed {
+                state.pressed_at = Some(now);
+            }
+        }
+    }
+    fn state(&self, id: ActionId) -> ButtonState {
+        self.states.get(id.index()).copied().unwrap_or_default()
+    }
+    /// Returns true if the action was pressed since the last step.
+    pub fn just_pressed(&self, id: ActionId) -> bool {
+        self.state(id).just_pressed
+    }
+    /// Returns true while the action is held down.
+    pub fn pressed(&self, id: ActionId) -> bool {
+        self.state(id).pressed
+    }
+    /// Returns true if the action was released since the last step.
+    pub fn just_released(&self, id: ActionId) -> bool {
+        self.state(id).just_released
+    }
+    /// How long the action has been held down, or None if it isn't.
+    pub fn held_for(&self, id: ActionId) -> Option<Duration> {
+        let state = self.state(id);
+        state.pressed.then(|| {
+            self.now
+                .saturating_sub(state.pressed_at.unwrap_or(self.now))
+        })
+    }
+    /// Returns true if the action was just pressed within [`DOUBLE_TAP_WINDOW`] of the press
+    /// before it.
+    pub fn double_tapped(&self, id: ActionId) -> bool {
+        self.state(id).double_tapped
+    }
+    /// Returns true on the step that completes the chord, when every action is held down and at
+    /// least one of them was just pressed.
+    pub fn chorded(&self, ids: &[ActionId]) -> bool {
+        ids.iter().all(|id| self.pressed(*id)) && ids.iter().any(|id| self.just_pressed(*id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{
+        event::ElementState,
+        keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
+    };
+
+    use super::*;
+    use crate::input::InputEvent;
+
+    fn escape(input: &mut Input, state: ElementState) {
+        input.handle_event(InputEvent::Key {
+            logical: Key::Named(NamedKey::Escape),
+            physical: PhysicalKey::Code(KeyCode::Escape),
+            state,
+            repeat: false,
+            text: None,
+        });
+    }
+
+    #[test]
+    fn input_timing_follows_real_time() {
+        let mut input = Input::new().unwrap();
+        let pause = input.action_id("Pause").unwrap();
+        let mut buttons = ButtonStates::new();
+        let mut time = GameTime::new(1.0 / 60.0);
+        let mut step = |input: &mut Input, buttons: &mut ButtonStates, real_secs: f32| {
+            time.advance(Duration::from_secs_f32(real_secs), false);
+            buttons.update(&time, input);
+        };
+
+        for (real_secs, double_tapped) in [(0.1, false), (0.3, true), (1.0, false)] {
+            escape(&mut input, ElementState::Pressed);
+            escape(&mut input, ElementState::Released);
+            step(&mut input, &mut buttons, real_secs);
+            assert!(buttons.just_pressed(pause));
+            assert!(buttons.just_released(pause));
+            assert_eq!(
+                buttons.double_tapped(pause),
+                double_tapped,
+                "at {real_secs}s"
+            );
+        }
+
+        // However many steps run in between
+        escape(&mut input, ElementState::Pressed);
+        step(&mut input, &mut buttons, 2.0);
+        for _ in 0..10 {
+            step(&mut input, &mut buttons, 2.0);
+        }
+        step(&mut input, &mut buttons, 2.5);
+        let held = buttons.held_for(pause).unwrap();
+        assert!((held.as_secs_f32() - 0.5).abs() < 1e-3, "held for {held:?}");
+    }
+}
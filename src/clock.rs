@@ -0,0 +1,155 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use crate::{
+    command::Commands,
+    event::EventWriter,
+    framework::{CompFlags, Component, Components, Entity},
+    game::{ComponentAccess, Process, ProcessStage},
+    render::RenderMessage,
+    resource::Resources,
+    timer::{SimInstant, Timer},
+};
+
+/// The game clock, stored as a resource and updated by the game loop before every step.
+#[derive(Debug, Clone)]
+pub struct GameTime {
+    /// Number of gameplay steps run so far
+    pub tick: u64,
+    /// Simulated time, which only advances during gameplay steps
+    pub elapsed: SimInstant,
+    /// Real time since the game started
+    pub real_elapsed: Duration,
+    /// Simulated seconds the current step advances by. Always the step period during gameplay
+    /// steps, and 0 for processes that run while paused
+    pub delta: f32,
+    /// Simulated seconds per gameplay step, set at startup
+    pub step_period: f32,
+}
+impl GameTime {
+    pub fn new(step_period: f32) -> Self {
+        Self {
+            tick: 0,
+            elapsed: SimInstant::default(),
+            real_elapsed: Duration::ZERO,
+            delta: 0.0,
+            step_period,
+        }
+    }
+    pub fn now(&self) -> SimInstant {
+        self.elapsed
+    }
+    /// Simulated seconds since the game started
+    pub fn elapsed_secs(&self) -> f64 {
+        self.elapsed.0.as_secs_f64()
+    }
+    /// Advances the clock for a gameplay step, or just real time if `paused`.
+    pub fn advance(&mut self, real_elapsed: Duration, paused: bool) {
+        self.real_elapsed = real_elapsed;
+        if paused {
+            self.delta = 0.0;
+        } else {
+            self.tick += 1;
+            self.elapsed = self.elapsed + Duration::from_secs_f32(self.step_period);
+            self.delta = self.step_period;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledTimer {
+    name: &'static str,
+    timer: Timer<SimInstant>,
+    repeating: bool,
+}
+
+/// Named timers on an entity, measured in simulated time so they freeze while gameplay is paused.
+/// The [`TimerProcess`] sends a [`TimerFinished`] event whenever one runs out.
+#[derive(Debug, Clone, Default)]
+pub struct Timers(Vec<ScheduledTimer>);
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Starts a timer that runs out `duration` after `now`, replacing any timer with the same
+    /// name. Repeating timers restart each time they run out.
+    pub fn start(
+        &mut self,
+        name: &'static str,
+        now: SimInstant,
+        duration: Duration,
+        repeating: bool,
+    ) {
+        self.cancel(name);
+        self.0.push(ScheduledTimer {
+            name,
+            timer: Timer::starting_at(now, duration),
+            repeating,
+        });
+    }
+    /// Returns false if there was no timer called `name`.
+    pub fn cancel(&mut self, name: &str) -> bool {
+        let len = self.0.len();
+        self.0.retain(|t| t.name != name);
+        self.0.len() != len
+    }
+    /// Time left on the timer called `name`, if it is running.
+    pub fn remaining(&self, name: &str, now: SimInstant) -> Option<Duration> {
+        let timer = &self.0.iter().find(|t| t.name == name)?.timer;
+        Some(timer.end().0.saturating_sub(now.0))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+impl Component for Timers {}
+
+/// Sent when one of an entity's [`Timers`] runs out.
+#[derive(Debug, Clone)]
+pub struct TimerFinished {
+    pub entity: Entity,
+    pub name: &'static str,
+}
+
+/// Checks every entity's [`Timers`] at the start of each gameplay step.
+#[derive(Debug)]
+pub struct TimerProcess {
+    finished: EventWriter<TimerFinished>,
+}
+impl Process for TimerProcess {
+    fn new(_: &Components, resources: &mut Resources, _: &Sender<RenderMessage>) -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            finished: EventWriter::new(resources),
+        }
+    }
+    fn name(&self) -> &'static str {
+        "Timer"
+    }
+    fn stage(&self) -> ProcessStage {
+        ProcessStage::Early
+    }
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::new(CompFlags::empty(), CompFlags::TIMERS).reads_resource::<GameTime>()
+    }
+    fn step(&mut self, components: &Components, resources: &Resources, _: &mut Commands) {
+        let now = resources.resource::<GameTime>().unwrap().now();
+        let mut timers = components.timers.write().unwrap();
+        for (entity, Timers(entity_timers)) in timers.iter_mut() {
+            entity_timers.retain_mut(|scheduled| {
+                if !scheduled.timer.check_at(now) {
+                    return true;
+                }
+                self.finished.send(TimerFinished {
+                    entity,
+                    name: scheduled.name,
+                });
+                if scheduled.repeating {
+                    scheduled.timer.check_reset_at(now);
+                }
+                scheduled.repeating
+            });
+        }
+    }
+}
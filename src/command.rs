@@ -0,0 +1,76 @@
+use std::fmt::Debug;
+
+use crate::{
+    framework::{Components, Entity, StoredComponent},
+    resource::Resources,
+};
+
+type Command = Box<dyn FnOnce(&Components, &mut Resources) + Send>;
+
+/// Queue of world changes recorded by a process during [`Process::step`](crate::game::Process::step).
+///
+/// Commands are applied between stages, in the order they were recorded, once no process holds any
+/// comptainer locks. This allows spawning and despawning while iterating over components.
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+impl Debug for Commands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Commands")
+            .field("queued", &self.queue.len())
+            .finish()
+    }
+}
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Allocates a new entity. It has no components until the queued additions are applied.
+    pub fn spawn(&mut self) -> Entity {
+        Entity::new()
+    }
+    /// Removes every component of `entity`.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |components, _| components.despawn(entity));
+    }
+    /// Removes every component of `entity` and all of its descendants.
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.add(move |components, _| components.despawn_recursive(entity));
+    }
+    /// Adds `component` to `entity`, replacing any it already has.
+    pub fn add_component<T: StoredComponent>(&mut self, entity: Entity, component: T) {
+        self.add(move |components, _| {
+            _ = T::comptainer(components)
+                .write()
+                .unwrap()
+                .add_component(entity, component);
+        });
+    }
+    pub fn remove_component<T: StoredComponent>(&mut self, entity: Entity) {
+        self.add(move |components, _| {
+            T::comptainer(components)
+                .write()
+                .unwrap()
+                .remove_component(entity);
+        });
+    }
+    pub fn insert_resource<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.add(move |_, resources| {
+            resources.insert_resource(value);
+        });
+    }
+    /// Queues an arbitrary change, ex. instantiating a prefab.
+    pub fn add(&mut self, command: impl FnOnce(&Components, &mut Resources) + Send + 'static) {
+        self.queue.push(Box::new(command));
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+    /// Applies and clears every queued command, in order.
+    pub fn apply(&mut self, components: &Components, resources: &mut Resources) {
+        for command in self.queue.drain(..) {
+            command(components, resources);
+        }
+    }
+}
@@ -0,0 +1,620 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Display,
+    num::NonZeroU64,
+    sync::{Arc, RwLock},
+};
+
+use egui::text::{CCursor, CCursorRange};
+use hydrolox_pga3d::prelude as pga;
+
+use crate::{
+    action::{Action, ActionValue},
+    command::Commands,
+    context::CONSOLE,
+    cvar::{CvarErr, Cvars},
+    framework::{Components, Entity},
+    game::UIElement,
+    input::{BindsErr, Input},
+    prefab::{Prefab, PrefabErr, PrefabOverrides},
+    resource::Resources,
+    scene::SceneErr,
+};
+
+/// Lines kept in the console's scrollback
+const MAX_LOG_LINES: usize = 500;
+
+#[derive(Debug)]
+pub enum ConsoleErr {
+    UnknownCommand(String),
+    /// The arguments didn't match the command's usage
+    Usage {
+        name: &'static str,
+        usage: &'static str,
+    },
+    BadArg(String),
+    Cvar(CvarErr),
+    Prefab(PrefabErr),
+    Scene(SceneErr),
+    Binds(BindsErr),
+}
+impl Display for ConsoleErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand(name) => writeln!(f, "Unknown command \"{name}\""),
+            Self::Usage { name, usage } => writeln!(f, "Usage: {name} {usage}"),
+            Self::BadArg(arg) => writeln!(f, "Invalid argument \"{arg}\""),
+            Self::Cvar(err) => writeln!(f, "{err}"),
+            Self::Prefab(err) => writeln!(f, "Prefab error: {err}"),
+            Self::Scene(err) => writeln!(f, "Scene error: {err}"),
+            Self::Binds(err) => writeln!(f, "Bindings error: {err}"),
+        }
+    }
+}
+impl Error for ConsoleErr {}
+impl From<CvarErr> for ConsoleErr {
+    fn from(value: CvarErr) -> Self {
+        Self::Cvar(value)
+    }
+}
+impl From<PrefabErr> for ConsoleErr {
+    fn from(value: PrefabErr) -> Self {
+        Self::Prefab(value)
+    }
+}
+impl From<SceneErr> for ConsoleErr {
+    fn from(value: SceneErr) -> Self {
+        Self::Scene(value)
+    }
+}
+impl From<BindsErr> for ConsoleErr {
+    fn from(value: BindsErr) -> Self {
+        Self::Binds(value)
+    }
+}
+
+/// What an argument refers to, used for tab completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Command,
+    Cvar,
+    /// Name of a file in `assets/prefabs`
+    Prefab,
+    /// Name of a file in `assets/scenes`
+    Scene,
+    Other,
+}
+
+type RunFn = fn(&[&str], &Components, &mut Resources) -> Result<String, ConsoleErr>;
+
+/// A command that can be typed into the console.
+///
+/// Commands run between stages like any other [`Commands`] entry, so they can freely change
+/// components and resources. The returned text is printed to the console.
+#[derive(Debug, Clone, Copy)]
+pub struct ConsoleCommand {
+    pub name: &'static str,
+    /// Argument syntax, ex. `<prefab> [x y z]`
+    pub usage: &'static str,
+    pub help: &'static str,
+    /// What each argument is, for tab completion
+    pub args: &'static [ArgKind],
+    pub run: RunFn,
+}
+
+/// Every command the console knows about, stored as a resource so any process can register more.
+#[derive(Debug, Default)]
+pub struct ConsoleCommands(Vec<ConsoleCommand>);
+impl ConsoleCommands {
+    pub fn with_builtins() -> Self {
+        let mut me = Self::default();
+        for command in BUILTIN_COMMANDS {
+            me.register(*command);
+        }
+        me
+    }
+    /// Adds `command`, replacing any command with the same name.
+    pub fn register(&mut self, command: ConsoleCommand) {
+        match self.0.binary_search_by_key(&command.name, |c| c.name) {
+            Ok(pos) => {
+                log::warn!("Replacing console command \"{}\"", command.name);
+                self.0[pos] = command;
+            }
+            Err(pos) => self.0.insert(pos, command),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.0
+            .binary_search_by_key(&name, |c| c.name)
+            .ok()
+            .map(|pos| &self.0[pos])
+    }
+    /// Every command, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = &ConsoleCommand> {
+        self.0.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// A command that was entered
+    Input,
+    Output,
+    Error,
+}
+
+/// The console's scrollback, stored as a resource so commands can print to it.
+#[derive(Debug, Default)]
+pub struct ConsoleLog {
+    lines: VecDeque<(LineKind, String)>,
+}
+impl ConsoleLog {
+    pub fn push(&mut self, kind: LineKind, text: impl Into<String>) {
+        if self.lines.len() == MAX_LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back((kind, text.into()));
+    }
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+    pub fn lines(&self) -> impl Iterator<Item = (LineKind, &str)> {
+        self.lines.iter().map(|(kind, text)| (*kind, text.as_str()))
+    }
+}
+
+fn parse_f32(arg: &str) -> Result<f32, ConsoleErr> {
+    arg.parse().map_err(|_| ConsoleErr::BadArg(arg.into()))
+}
+
+const BUILTIN_COMMANDS: &[ConsoleCommand] = &[
+    ConsoleCommand {
+        name: "help",
+        usage: "[command]",
+        help: "Lists every command, or describes one",
+        args: &[ArgKind::Command],
+        run: |args, _, resources| {
+            let commands = resources.resource::<ConsoleCommands>().unwrap();
+            match args {
+                [] => Ok(commands
+                    .iter()
+                    .map(|c| format!("{} {} - {}", c.name, c.usage, c.help))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                [name] => {
+                    let c = commands
+                        .get(name)
+                        .ok_or_else(|| ConsoleErr::UnknownCommand(name.to_string()))?;
+                    Ok(format!("{} {}\n{}", c.name, c.usage, c.help))
+                }
+                _ => Err(ConsoleErr::Usage {
+                    name: "help",
+                    usage: "[command]",
+                }),
+            }
+        },
+    },
+    ConsoleCommand {
+        name: "clear",
+        usage: "",
+        help: "Clears the console",
+        args: &[],
+        run: |_, _, resources| {
+            resources.resource_mut::<ConsoleLog>().unwrap().clear();
+            Ok(String::new())
+        },
+    },
+    ConsoleCommand {
+        name: "get",
+        usage: "[cvar]",
+        help: "Shows the value of a cvar, or of every cvar",
+        args: &[ArgKind::Cvar],
+        run: |args, _, resources| {
+            let cvars = resources.resource::<Cvars>().unwrap();
+            let describe = |name: &str| {
+                let cvar = cvars
+                    .cvar(name)
+                    .ok_or_else(|| CvarErr::Unknown(name.into()))?;
+                Ok::<_, ConsoleErr>(format!(
+                    "{name} = {} (default {}) - {}",
+                    cvar.value, cvar.default, cvar.help
+                ))
+            };
+            match args {
+                [] => Ok(cvars
+                    .iter()
+                    .map(|(name, _)| describe(name))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join("\n")),
+                [name] => describe(name),
+                _ => Err(ConsoleErr::Usage {
+                    name: "get",
+                    usage: "[cvar]",
+                }),
+            }
+        },
+    },
+    ConsoleCommand {
+        name: "set",
+        usage: "<cvar> <value>",
+        help: "Sets a cvar and saves it to cvars.yaml",
+        args: &[ArgKind::Cvar, ArgKind::Other],
+        run: |args, _, resources| {
+            let [name, value @ ..] = args else {
+                return Err(ConsoleErr::Usage {
+                    name: "set",
+                    usage: "<cvar> <value>",
+                });
+            };
+            if value.is_empty() {
+                return Err(ConsoleErr::Usage {
+                    name: "set",
+                    usage: "<cvar> <value>",
+                });
+            }
+            let mut cvars = resources.resource_mut::<Cvars>().unwrap();
+            cvars.set_str(name, &value.join(" "))?;
+            cvars.save()?;
+            Ok(format!("{name} = {}", cvars.cvar(name).unwrap().value))
+        },
+    },
+    ConsoleCommand {
+        name: "spawn",
+        usage: "<prefab> [x y z]",
+        help: "Instantiates a prefab, at the origin or the given position",
+        args: &[ArgKind::Prefab],
+        run: |args, components, _| {
+            let (name, position) = match args {
+                [name] => (name, [0.0; 3]),
+                [name, x, y, z] => (name, [parse_f32(x)?, parse_f32(y)?, parse_f32(z)?]),
+                _ => {
+                    return Err(ConsoleErr::Usage {
+                        name: "spawn",
+                        usage: "<prefab> [x y z]",
+                    })
+                }
+            };
+            let entities = Prefab::load(name)?.instantiate(
+                components,
+                pga::Motor::from_translation(position[0], position[1], position[2]),
+                &PrefabOverrides::new(),
+            )?;
+            let entities: Vec<String> = entities.iter().map(Entity::to_string).collect();
+            Ok(format!("Spawned {}", entities.join(", ")))
+        },
+    },
+    ConsoleCommand {
+        name: "despawn",
+        usage: "<entity>",
+        help: "Removes an entity and all of its descendants",
+        args: &[ArgKind::Other],
+        run: |args, components, _| {
+            let [id] = args else {
+                return Err(ConsoleErr::Usage {
+                    name: "despawn",
+                    usage: "<entity>",
+                });
+            };
+            let entity = Entity::from_raw(
+                id.parse::<NonZeroU64>()
+                    .map_err(|_| ConsoleErr::BadArg(id.to_string()))?,
+            );
+            components.despawn_recursive(entity);
+            Ok(format!("Despawned {entity}"))
+        },
+    },
+    ConsoleCommand {
+        name: "reload_binds",
+        usage: "",
+        help: "Reloads binds.yaml",
+        args: &[],
+        run: |_, _, resources| {
+            resources
+                .resource_mut::<Input>()
+                .unwrap()
+                .reload_bindings()?;
+            Ok("Reloaded bindings".into())
+        },
+    },
+    ConsoleCommand {
+        name: "context",
+        usage: "[push|pop <context>]",
+        help: "Lists the active input contexts from the top down, or pushes or pops one",
+        args: &[ArgKind::Other, ArgKind::Other],
+        run: |args, _, resources| {
+            let mut input = resources.resource_mut::<Input>().unwrap();
+            let contexts = input.contexts_mut();
+            match args {
+                [] => Ok(contexts.iter().collect::<Vec<_>>().join(", ")),
+                ["push", name] if contexts.push(name) => Ok(format!("Pushed \"{name}\"")),
+                ["pop", name] if contexts.pop(name) => Ok(format!("Popped \"{name}\"")),
+                ["push" | "pop", name] => Err(ConsoleErr::BadArg(name.to_string())),
+                _ => Err(ConsoleErr::Usage {
+                    name: "context",
+                    usage: "[push|pop <context>]",
+                }),
+            }
+        },
+    },
+    ConsoleCommand {
+        name: "save_scene",
+        usage: "<name>",
+        help: "Saves every serializable component to assets/scenes/<name>.yaml",
+        args: &[ArgKind::Scene],
+        run: |args, components, _| {
+            let [name] = args else {
+                return Err(ConsoleErr::Usage {
+                    name: "save_scene",
+                    usage: "<name>",
+                });
+            };
+            components.save_scene(name)?;
+            Ok(format!("Saved scene \"{name}\""))
+        },
+    },
+    ConsoleCommand {
+        name: "load_scene",
+        usage: "<name>",
+        help: "Adds the entities in assets/scenes/<name>.yaml to the world",
+        args: &[ArgKind::Scene],
+        run: |args, components, _| {
+            let [name] = args else {
+                return Err(ConsoleErr::Usage {
+                    name: "load_scene",
+                    usage: "<name>",
+                });
+            };
+            let entities = components.load_scene(name)?;
+            Ok(format!(
+                "Loaded scene \"{name}\" with {} entities",
+                entities.len()
+            ))
+        },
+    },
+];
+
+/// Names of the YAML files in `dir`, without their extension
+fn asset_names(dir: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "yaml").then(|| path.file_stem()?.to_str().map(String::from))?
+        })
+        .collect()
+}
+
+/// Drop-down console for running [`ConsoleCommand`]s. Toggled by its handler's action,
+/// `ToggleConsole` by default.
+///
+/// Tab completes command names and arguments, and the up and down arrows step through history.
+#[derive(Debug)]
+pub struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+    /// Position while stepping through history, None while editing a new line
+    history_pos: Option<usize>,
+    /// The input needs focus and its cursor moved to the end
+    refocus: bool,
+    log: Arc<RwLock<ConsoleLog>>,
+    commands: Arc<RwLock<ConsoleCommands>>,
+    cvars: Arc<RwLock<Cvars>>,
+}
+impl Console {
+    /// `resources` must contain the [`ConsoleLog`], [`ConsoleCommands`] and [`Cvars`].
+    pub fn new(resources: &Resources) -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            refocus: false,
+            log: resources.shared().unwrap(),
+            commands: resources.shared().unwrap(),
+            cvars: resources.shared().unwrap(),
+        }
+    }
+
+    fn submit(&mut self, commands: &mut Commands) {
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        self.history_pos = None;
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.into());
+        }
+
+        let mut log = self.log.write().unwrap();
+        log.push(LineKind::Input, format!("> {line}"));
+        let name = line.split_whitespace().next().unwrap();
+        let Some(&command) = self.commands.read().unwrap().get(name) else {
+            log.push(
+                LineKind::Error,
+                ConsoleErr::UnknownCommand(name.into())
+                    .to_string()
+                    .trim_end(),
+            );
+            return;
+        };
+
+        let line = line.to_string();
+        commands.add(move |components, resources| {
+            let args: Vec<&str> = line.split_whitespace().skip(1).collect();
+            let result = (command.run)(&args, components, resources);
+            let mut log = resources.resource_mut::<ConsoleLog>().unwrap();
+            match result {
+                Ok(output) if output.is_empty() => (),
+                Ok(output) => log.push(LineKind::Output, output),
+                Err(err) => log.push(LineKind::Error, err.to_string().trim_end()),
+            }
+        });
+    }
+
+    fn step_history(&mut self, back: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        self.history_pos = match (self.history_pos, back) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) => (pos + 1 < self.history.len()).then_some(pos + 1),
+        };
+        self.input = self
+            .history_pos
+            .map(|pos| self.history[pos].clone())
+            .unwrap_or_default();
+        self.refocus = true;
+    }
+
+    fn candidates(&self, kind: ArgKind) -> Vec<String> {
+        match kind {
+            ArgKind::Command => self
+                .commands
+                .read()
+                .unwrap()
+                .iter()
+                .map(|c| c.name.into())
+                .collect(),
+            ArgKind::Cvar => self
+                .cvars
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(name, _)| name.into())
+                .collect(),
+            ArgKind::Prefab => asset_names("assets/prefabs"),
+            ArgKind::Scene => asset_names("assets/scenes"),
+            ArgKind::Other => Vec::new(),
+        }
+    }
+
+    /// Completes the word being typed, listing the options if there is more than one.
+    fn complete(&mut self) {
+        let mut words: Vec<&str> = self.input.split_whitespace().collect();
+        let partial = if self.input.ends_with(' ') || words.is_empty() {
+            ""
+        } else {
+            words.pop().unwrap()
+        };
+        let kind = match words.split_first() {
+            None => ArgKind::Command,
+            Some((name, args)) => self
+                .commands
+                .read()
+                .unwrap()
+                .get(name)
+                .and_then(|c| c.args.get(args.len()).copied())
+                .unwrap_or(ArgKind::Other),
+        };
+
+        let mut matches: Vec<String> = self
+            .candidates(kind)
+            .into_iter()
+            .filter(|c| c.starts_with(partial))
+            .collect();
+        matches.sort();
+        let Some(first) = matches.first() else {
+            return;
+        };
+        let completed = if matches.len() == 1 {
+            format!("{first} ")
+        } else {
+            let common_len = matches[1..].iter().fold(first.len(), |len, m| {
+                first
+                    .chars()
+                    .zip(m.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count()
+                    .min(len)
+            });
+            self.log
+                .write()
+                .unwrap()
+                .push(LineKind::Output, matches.join("  "));
+            first.chars().take(common_len).collect()
+        };
+
+        words.push(&completed);
+        self.input = words.join(" ");
+        self.refocus = true;
+    }
+}
+impl UIElement for Console {
+    fn active(&self) -> bool {
+        self.open
+    }
+    fn handle_action(&mut self, action: Action) {
+        if action.value == ActionValue::Pressed {
+            self.open = !self.open;
+            self.refocus = self.open;
+        }
+    }
+    fn input_context(&self) -> Option<&'static str> {
+        Some(CONSOLE)
+    }
+    fn show(&mut self, ctx: &egui::Context, _: &Components, commands: &mut Commands) {
+        let input_id = egui::Id::new("console input");
+        if ctx.memory(|m| m.has_focus(input_id)) {
+            let pressed =
+                |key| ctx.input_mut(|input| input.consume_key(egui::Modifiers::NONE, key));
+            if pressed(egui::Key::Tab) {
+                self.complete();
+            }
+            if pressed(egui::Key::ArrowUp) {
+                self.step_history(true);
+            }
+            if pressed(egui::Key::ArrowDown) {
+                self.step_history(false);
+            }
+        }
+
+        egui::TopBottomPanel::top("console")
+            .resizable(true)
+            .default_height(240.0)
+            .show(ctx, |ui| {
+                let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .max_height(ui.available_height() - input_height)
+                    .show(ui, |ui| {
+                        for (kind, text) in self.log.read().unwrap().lines() {
+                            let text = egui::RichText::new(text).monospace();
+                            ui.label(match kind {
+                                LineKind::Input => text.weak(),
+                                LineKind::Output => text,
+                                LineKind::Error => text.color(ui.visuals().error_fg_color),
+                            });
+                        }
+                    });
+
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .id(input_id)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY)
+                        .lock_focus(true),
+                );
+                if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                    self.submit(commands);
+                    self.refocus = true;
+                }
+                if self.refocus {
+                    self.refocus = false;
+                    response.request_focus();
+                    if let Some(mut state) = egui::TextEdit::load_state(ctx, input_id) {
+                        let end = CCursor::new(self.input.chars().count());
+                        state.cursor.set_char_range(Some(CCursorRange::one(end)));
+                        state.store(ctx, input_id);
+                    }
+                }
+            });
+    }
+}
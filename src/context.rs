@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActionId, ActionManifest};
+
+/// Default context, active from startup
+pub const GAMEPLAY: &str = "gameplay";
+/// Active while the game is paused
+pub const MENU: &str = "menu";
+/// Active while the settings panel is open
+pub const SETTINGS: &str = "settings";
+/// Active while the developer console is open
+pub const CONSOLE: &str = "console";
+
+/// What a context keeps from reaching the contexts below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Consume {
+    /// Nothing, lower contexts still receive the context's own actions
+    None,
+    /// Only the context's own actions
+    Bound,
+    /// Every action, including ones the context doesn't use
+    All,
+}
+
+/// A named set of actions that can be pushed onto the [`ContextStack`], defined in
+/// `actions.yaml` along with the actions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextDef {
+    name: String,
+    /// Higher priorities sit above lower ones in the stack regardless of push order
+    priority: i32,
+    consume: Consume,
+    actions: Vec<String>,
+}
+
+#[derive(Debug)]
+struct InputContext {
+    def: ContextDef,
+    actions: Vec<ActionId>,
+}
+
+/// Active input contexts, ordered from highest to lowest priority. Each action goes to the highest
+/// context that uses it, unless a context above that one consumes it first.
+#[derive(Debug)]
+pub struct ContextStack {
+    contexts: Vec<InputContext>,
+    /// Indices into `contexts`, topmost first
+    active: Vec<usize>,
+}
+impl ContextStack {
+    /// Creates the stack with only [`GAMEPLAY`] active. Warns about any context the engine pushes
+    /// itself that the manifest doesn't define.
+    pub fn new(manifest: &ActionManifest) -> Self {
+        let contexts = manifest
+            .contexts()
+            .iter()
+            .map(|def| InputContext {
+                def: def.clone(),
+                actions: def
+                    .actions
+                    .iter()
+                    .filter_map(|name| {
+                        let id = manifest.id(name);
+                        if id.is_none() {
+                            log::warn!(
+                                "Input context \"{}\" uses unknown action \"{name}\"",
+                                def.name
+                            );
+                        }
+                        id
+                    })
+                    .collect(),
+            })
+            .collect();
+        let mut me = Self {
+            contexts,
+            active: Vec::new(),
+        };
+        for name in [GAMEPLAY, MENU, SETTINGS, CONSOLE] {
+            if !me.contexts.iter().any(|c| c.def.name == name) {
+                log::warn!("Input context \"{name}\" is missing from the action manifest");
+            }
+        }
+        me.push(GAMEPLAY);
+        me
+    }
+    /// Activates the context, placing it above active contexts of the same or lower priority.
+    /// Returns false if there is no context called `name`.
+    pub fn push(&mut self, name: &str) -> bool {
+        let Some(index) = self.contexts.iter().position(|c| c.def.name == name) else {
+            return false;
+        };
+        if !self.active.contains(&index) {
+            let priority = self.contexts[index].def.priority;
+            let at = self
+                .active
+                .iter()
+                .position(|&i| self.contexts[i].def.priority <= priority)
+                .unwrap_or(self.active.len());
+            self.active.insert(at, index);
+        }
+        true
+    }
+    /// Returns false if the context wasn't active.
+    pub fn pop(&mut self, name: &str) -> bool {
+        let len = self.active.len();
+        self.active.retain(|&i| self.contexts[i].def.name != name);
+        self.active.len() != len
+    }
+    pub fn set_active(&mut self, name: &str, active: bool) {
+        if active {
+            self.push(name);
+        } else {
+            self.pop(name);
+        }
+    }
+    pub fn is_active(&self, name: &str) -> bool {
+        self.active
+            .iter()
+            .any(|&i| self.contexts[i].def.name == name)
+    }
+    /// Names of the active contexts, topmost first
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.active
+            .iter()
+            .map(|&i| self.contexts[i].def.name.as_str())
+    }
+    /// Returns true if the action reaches `context`, which must be active and use the action.
+    pub fn allows(&self, context: &str, id: ActionId) -> bool {
+        for &i in &self.active {
+            let InputContext { def, actions } = &self.contexts[i];
+            let uses = actions.contains(&id);
+            if def.name == context {
+                return uses;
+            }
+            match def.consume {
+                Consume::All => return false,
+                Consume::Bound if uses => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+    /// The highest context the action reaches, or None if it's blocked or unused.
+    pub fn claimed_by(&self, id: ActionId) -> Option<&str> {
+        for &i in &self.active {
+            let InputContext { def, actions } = &self.contexts[i];
+            if actions.contains(&id) {
+                return Some(&def.name);
+            }
+            if def.consume == Consume::All {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> ActionManifest {
+        serde_yml::from_str(include_str!("../actions.yaml")).unwrap()
+    }
+
+    #[test]
+    fn committed_manifest_defines_engine_contexts() {
+        let manifest = manifest();
+        for def in manifest.contexts() {
+            for name in &def.actions {
+                assert!(manifest.id(name).is_some(), "{} uses {name}", def.name);
+            }
+        }
+        let mut stack = ContextStack::new(&manifest);
+        for name in [GAMEPLAY, MENU, SETTINGS, CONSOLE] {
+            assert!(stack.push(name), "{name} is missing");
+        }
+    }
+
+    #[test]
+    fn higher_contexts_claim_and_consume_actions() {
+        let manifest = manifest();
+        let [pause, move_action] = ["Pause", "Move"].map(|name| manifest.id(name).unwrap());
+        let mut stack = ContextStack::new(&manifest);
+        assert_eq!(stack.claimed_by(move_action), Some(GAMEPLAY));
+
+        stack.push(MENU);
+        assert_eq!(stack.claimed_by(move_action), None);
+        assert_eq!(stack.claimed_by(pause), Some(MENU));
+        assert!(!stack.allows(GAMEPLAY, pause));
+
+        stack.push(CONSOLE);
+        assert_eq!(stack.claimed_by(pause), None);
+        assert_eq!(
+            stack.iter().collect::<Vec<_>>(),
+            vec![CONSOLE, MENU, GAMEPLAY]
+        );
+    }
+}
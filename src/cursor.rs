@@ -0,0 +1,35 @@
+use winit::window::{CursorGrabMode, Window};
+
+/// How the OS cursor behaves over the window. Chosen by the UI process each step and applied to
+/// the window by the app.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorMode {
+    /// Visible and free to move, for interacting with UI. Mouse motion isn't sent as `Look`
+    #[default]
+    Free,
+    /// Hidden and held in place, so mouse motion only drives mouselook
+    Locked,
+}
+impl CursorMode {
+    /// Grabs or releases the cursor. Platforms that can't lock the cursor in place confine it to
+    /// the window instead.
+    pub fn apply(self, window: &Window) {
+        match self {
+            Self::Free => {
+                if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+                    log::warn!("Failed to release cursor: {err}");
+                }
+                window.set_cursor_visible(true);
+            }
+            Self::Locked => {
+                if let Err(err) = window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                {
+                    log::warn!("Failed to grab cursor: {err}");
+                }
+                window.set_cursor_visible(false);
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::BTreeMap, error::Error, fmt::Display, fs::File};
+
+use serde::{Deserialize, Serialize};
+use serde_yml as yml;
+
+const CVARS_PATH: &str = "cvars.yaml";
+
+#[derive(Debug)]
+pub enum CvarErr {
+    Yaml(yml::Error),
+    IO(std::io::Error),
+    Unknown(String),
+    /// The value couldn't be parsed as the cvar's type
+    BadValue {
+        name: String,
+        value: String,
+        type_name: &'static str,
+    },
+}
+impl Display for CvarErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(err) => writeln!(f, "Yaml error: {err}"),
+            Self::IO(err) => writeln!(f, "IO error: {err}"),
+            Self::Unknown(name) => writeln!(f, "Unknown cvar \"{name}\""),
+            Self::BadValue {
+                name,
+                value,
+                type_name,
+            } => writeln!(f, "Cvar \"{name}\" is a {type_name}, got \"{value}\""),
+        }
+    }
+}
+impl Error for CvarErr {}
+impl From<yml::Error> for CvarErr {
+    fn from(value: yml::Error) -> Self {
+        Self::Yaml(value)
+    }
+}
+impl From<std::io::Error> for CvarErr {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+impl CvarValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+        }
+    }
+    /// Parses `s` as the same type as `self`.
+    fn parse_as(&self, s: &str) -> Option<Self> {
+        Some(match self {
+            Self::Bool(_) => Self::Bool(match s {
+                "1" | "true" | "on" => true,
+                "0" | "false" | "off" => false,
+                _ => return None,
+            }),
+            Self::Int(_) => Self::Int(s.parse().ok()?),
+            Self::Float(_) => Self::Float(s.parse().ok()?),
+            Self::String(_) => Self::String(s.into()),
+        })
+    }
+    /// Converts `self` to the same type as `like`, for values loaded from the config file.
+    fn coerce(self, like: &Self) -> Option<Self> {
+        match (self, like) {
+            (Self::Int(i), Self::Float(_)) => Some(Self::Float(i as f64)),
+            (value, like) if std::mem::discriminant(&value) == std::mem::discriminant(like) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+impl Display for CvarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x}"),
+            Self::String(s) => write!(f, "\"{s}\""),
+        }
+    }
+}
+
+/// Rust types that can be stored in a cvar.
+pub trait CvarType: Sized {
+    fn into_value(self) -> CvarValue;
+    fn from_value(value: &CvarValue) -> Option<Self>;
+}
+impl CvarType for bool {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Bool(self)
+    }
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+impl CvarType for i64 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Int(self)
+    }
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+impl CvarType for f64 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Float(self)
+    }
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+impl CvarType for f32 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Float(self as f64)
+    }
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        f64::from_value(value).map(|x| x as f32)
+    }
+}
+impl CvarType for String {
+    fn into_value(self) -> CvarValue {
+        CvarValue::String(self)
+    }
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cvar {
+    pub value: CvarValue,
+    pub default: CvarValue,
+    pub help: &'static str,
+}
+
+/// Named, typed settings that any subsystem can register, stored as a resource. Values are saved to
+/// `cvars.yaml` and can be changed from the console with `set`.
+#[derive(Debug, Default)]
+pub struct Cvars {
+    vars: BTreeMap<String, Cvar>,
+    /// Values from the config file, including any for cvars that haven't been registered yet
+    saved: BTreeMap<String, CvarValue>,
+}
+impl Cvars {
+    /// Loads saved values from `cvars.yaml`, starting empty if it doesn't exist or can't be read.
+    pub fn load() -> Self {
+        let saved = match File::open(CVARS_PATH) {
+            Ok(file) => yml::from_reader(file).unwrap_or_else(|err| {
+                log::error!("Failed to read {CVARS_PATH}, using defaults: {err}");
+                BTreeMap::new()
+            }),
+            Err(_) => {
+                log::info!("Cvars file not found, using defaults");
+                BTreeMap::new()
+            }
+        };
+        Self {
+            vars: BTreeMap::new(),
+            saved,
+        }
+    }
+    pub fn save(&self) -> Result<(), CvarErr> {
+        let mut values = self.saved.clone();
+        for (name, cvar) in &self.vars {
+            values.insert(name.clone(), cvar.value.clone());
+        }
+        let file = File::create(CVARS_PATH)?;
+        yml::to_writer(&file, &values)?;
+        Ok(())
+    }
+    /// Adds a cvar, taking its value from the config file if it was saved there. Registering the
+    /// same name again only replaces the default and help text.
+    pub fn register<T: CvarType>(&mut self, name: &str, default: T, help: &'static str) {
+        let default = default.into_value();
+        let value = match self.saved.get(name).cloned() {
+            Some(saved) => saved.coerce(&default).unwrap_or_else(|| {
+                log::warn!(
+                    "Saved value of cvar \"{name}\" should be a {}, using default",
+                    default.type_name()
+                );
+                default.clone()
+            }),
+            None => default.clone(),
+        };
+        self.vars
+            .entry(name.into())
+            .and_modify(|cvar| {
+                cvar.default = default.clone();
+                cvar.help = help;
+            })
+            .or_insert(Cvar {
+                value,
+                default,
+                help,
+            });
+    }
+    /// Returns the value of the cvar, or None if it isn't registered or is a different type.
+    pub fn get<T: CvarType>(&self, name: &str) -> Option<T> {
+        T::from_value(&self.vars.get(name)?.value)
+    }
+    pub fn cvar(&self, name: &str) -> Option<&Cvar> {
+        self.vars.get(name)
+    }
+    /// Sets the cvar from text, parsed according to the cvar's type.
+    pub fn set_str(&mut self, name: &str, value: &str) -> Result<(), CvarErr> {
+        let cvar = self
+            .vars
+            .get_mut(name)
+            .ok_or_else(|| CvarErr::Unknown(name.into()))?;
+        cvar.value = cvar
+            .default
+            .parse_as(value)
+            .ok_or_else(|| CvarErr::BadValue {
+                name: name.into(),
+                value: value.into(),
+                type_name: cvar.default.type_name(),
+            })?;
+        Ok(())
+    }
+    /// Every registered cvar, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Cvar)> {
+        self.vars.iter().map(|(name, cvar)| (name.as_str(), cvar))
+    }
+}
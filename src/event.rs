@@ -0,0 +1,218 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+use crate::resource::Resources;
+
+/// Queue of events of type `T`, stored as a resource and shared by every [`EventWriter<T>`] and
+/// [`EventReader<T>`].
+///
+/// Events are double-buffered: everything sent during a step survives until the end of the next
+/// one, so readers see each event once no matter which order they run in relative to the writer.
+/// Events are dropped early once every reader has seen them.
+#[derive(Debug)]
+pub struct Events<T> {
+    /// Shared so readers can hold onto events without keeping the queue locked
+    events: VecDeque<Arc<T>>,
+    /// Id of the event at the front of `events`
+    first_id: u64,
+    /// Id of the first event sent during the current step
+    step_start_id: u64,
+    /// Id of the next event each registered reader will read
+    reader_cursors: Vec<Option<u64>>,
+}
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            events: VecDeque::new(),
+            first_id: 0,
+            step_start_id: 0,
+            reader_cursors: Vec::new(),
+        }
+    }
+}
+impl<T> Events<T> {
+    fn end_id(&self) -> u64 {
+        self.first_id + self.events.len() as u64
+    }
+    pub fn send(&mut self, event: T) {
+        self.events.push_back(Arc::new(event));
+    }
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+    fn add_reader(&mut self) -> usize {
+        let cursor = Some(self.end_id());
+        if let Some(pos) = self.reader_cursors.iter().position(Option::is_none) {
+            self.reader_cursors[pos] = cursor;
+            pos
+        } else {
+            self.reader_cursors.push(cursor);
+            self.reader_cursors.len() - 1
+        }
+    }
+    fn remove_reader(&mut self, id: usize) {
+        self.reader_cursors[id] = None;
+    }
+    /// Returns the events the reader hasn't seen yet, and marks them as seen.
+    fn read(&mut self, reader_id: usize) -> impl Iterator<Item = &Arc<T>> {
+        let end_id = self.end_id();
+        let cursor = self.reader_cursors[reader_id]
+            .replace(end_id)
+            .unwrap()
+            .max(self.first_id);
+        self.events.range((cursor - self.first_id) as usize..)
+    }
+    /// Drops events sent before the previous step, and any every reader has already seen.
+    /// Called once at the end of every game step.
+    pub fn update(&mut self) {
+        let end_id = self.end_id();
+        let all_seen = self
+            .reader_cursors
+            .iter()
+            .flatten()
+            .copied()
+            .min()
+            .unwrap_or(end_id);
+        let drop_to = all_seen.max(self.step_start_id);
+        self.events.drain(..(drop_to - self.first_id) as usize);
+        self.first_id = drop_to;
+        self.step_start_id = end_id;
+    }
+}
+
+fn update_events<T: Send + Sync + 'static>(resources: &Resources) {
+    resources.resource_mut::<Events<T>>().unwrap().update();
+}
+
+impl Resources {
+    /// Returns the shared [`Events<T>`] resource, adding it first if it doesn't exist yet.
+    fn events<T: Send + Sync + 'static>(&mut self) -> Arc<RwLock<Events<T>>> {
+        if !self.contains_resource::<Events<T>>() {
+            self.insert_resource(Events::<T>::default());
+            self.add_step_hook(update_events::<T>);
+        }
+        self.shared::<Events<T>>().unwrap()
+    }
+}
+
+/// Handle for sending events of type `T`. Usually created in [`Process::new`](crate::game::Process::new).
+#[derive(Debug)]
+pub struct EventWriter<T> {
+    events: Arc<RwLock<Events<T>>>,
+}
+impl<T> Clone for EventWriter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            events: self.events.clone(),
+        }
+    }
+}
+impl<T: Send + Sync + 'static> EventWriter<T> {
+    pub fn new(resources: &mut Resources) -> Self {
+        Self {
+            events: resources.events(),
+        }
+    }
+    pub fn send(&self, event: T) {
+        self.events.write().unwrap().send(event);
+    }
+}
+
+/// Handle for receiving events of type `T`. Each reader sees every event sent after it was created
+/// exactly once, as long as it reads at least once per step.
+#[derive(Debug)]
+pub struct EventReader<T> {
+    events: Arc<RwLock<Events<T>>>,
+    id: usize,
+}
+impl<T: Send + Sync + 'static> EventReader<T> {
+    pub fn new(resources: &mut Resources) -> Self {
+        let events = resources.events();
+        let id = events.write().unwrap().add_reader();
+        Self { events, id }
+    }
+    /// Calls `func` on every event this reader hasn't seen yet. The queue isn't locked while
+    /// `func` runs, so it can send and read events of the same type.
+    pub fn read(&mut self, mut func: impl FnMut(&T)) {
+        let unread: Vec<Arc<T>> = self
+            .events
+            .write()
+            .unwrap()
+            .read(self.id)
+            .cloned()
+            .collect();
+        for event in &unread {
+            func(event);
+        }
+    }
+}
+impl<T: Clone + Send + Sync + 'static> EventReader<T> {
+    /// Returns a copy of every event this reader hasn't seen yet.
+    pub fn read_cloned(&mut self) -> Vec<T> {
+        self.events
+            .write()
+            .unwrap()
+            .read(self.id)
+            .map(|event| T::clone(event))
+            .collect()
+    }
+}
+impl<T> Drop for EventReader<T> {
+    fn drop(&mut self) {
+        if let Ok(mut events) = self.events.write() {
+            events.remove_reader(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_last_until_the_end_of_the_next_step() {
+        let mut resources = Resources::new();
+        let writer = EventWriter::<u32>::new(&mut resources);
+        let mut early = EventReader::<u32>::new(&mut resources);
+        let mut late = EventReader::<u32>::new(&mut resources);
+
+        writer.send(1);
+        assert_eq!(early.read_cloned(), vec![1]);
+        assert_eq!(early.read_cloned(), Vec::<u32>::new());
+        resources.run_step_hooks();
+
+        // Readers that run before the writer in a step still see its events from the last one
+        writer.send(2);
+        assert_eq!(late.read_cloned(), vec![1, 2]);
+        resources.run_step_hooks();
+        writer.send(3);
+        resources.run_step_hooks();
+
+        // Unread events are dropped after the step following the one they were sent in
+        resources.run_step_hooks();
+        assert_eq!(early.read_cloned(), Vec::<u32>::new());
+        assert_eq!(late.read_cloned(), Vec::<u32>::new());
+        assert!(resources.resource::<Events<u32>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_can_send_events() {
+        let mut resources = Resources::new();
+        let writer = EventWriter::<u32>::new(&mut resources);
+        let mut reader = EventReader::<u32>::new(&mut resources);
+
+        writer.send(1);
+        let mut seen = Vec::new();
+        reader.read(|&n| {
+            seen.push(n);
+            writer.send(n + 1);
+        });
+        reader.read(|&n| seen.push(n));
+        assert_eq!(seen, vec![1, 2]);
+    }
+}
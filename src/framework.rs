@@ -9,13 +9,14 @@ use std::{
 };
 
 use ahash::AHashMap;
+use bitflags::bitflags;
 use serde::{
     de::{Unexpected, Visitor},
     ser::SerializeMap,
     Deserialize, Serialize,
 };
 
-use crate::{game, physics, render};
+use crate::{clock, game, physics, render};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -29,6 +30,11 @@ mod entity_impl {
     pub fn next_id() -> u64 {
         NEXT_ID.fetch_add(1, Ordering::SeqCst)
     }
+
+    static NEXT_COMPTAINER_ID: AtomicU64 = AtomicU64::new(0);
+    pub fn next_comptainer_id() -> u64 {
+        NEXT_COMPTAINER_ID.fetch_add(1, Ordering::Relaxed)
+    }
 }
 impl Entity {
     pub const RESERVED: Self = Self {
@@ -39,6 +45,10 @@ impl Entity {
             id: NonZeroU64::new(entity_impl::next_id()).unwrap(),
         }
     }
+    /// Creates a handle from an existing id, ex. one read from a file. Doesn't allocate a new id.
+    pub fn from_raw(id: NonZeroU64) -> Self {
+        Self { id }
+    }
 }
 impl Serialize for Entity {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -177,7 +187,35 @@ impl Display for Entity {
     }
 }
 
-pub trait Component: 'static + Debug {}
+/// Maps entity ids from a serialized scene to the fresh ids they were given when loaded.
+#[derive(Debug, Default)]
+pub struct EntityMap(AHashMap<Entity, Entity>);
+impl EntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the new entity for `old`, allocating one if it hasn't been mapped yet.
+    pub fn get_or_insert(&mut self, old: Entity) -> Entity {
+        *self.0.entry(old).or_insert_with(Entity::new)
+    }
+    /// Returns the new entity for `old`, or `old` itself if it isn't part of the map.
+    pub fn map(&self, old: Entity) -> Entity {
+        if let Some(&new) = self.0.get(&old) {
+            new
+        } else {
+            log::warn!("Entity {old} referenced in scene but not part of it, leaving unmapped");
+            old
+        }
+    }
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.values().copied()
+    }
+}
+
+pub trait Component: 'static + Debug {
+    /// Updates any entities this component refers to after it is loaded with remapped ids.
+    fn map_entities(&mut self, _map: &EntityMap) {}
+}
 
 #[derive(Debug, Clone)]
 pub struct CompIter<'a, T> {
@@ -253,10 +291,47 @@ where
 impl<T> ExactSizeIterator for CompIterMut<'_, T> where T: Component {}
 impl<T> FusedIterator for CompIterMut<'_, T> where T: Component {}
 
+/// The ticks at which a component was last added and changed, see [`Comptainer::change_tick`].
+#[derive(Debug, Clone, Copy)]
+struct ChangeTicks {
+    added: u64,
+    changed: u64,
+}
+impl ChangeTicks {
+    fn new(tick: u64) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+/// Tracks which changes to a comptainer a system has already seen.
+///
+/// Created with [`Comptainer::change_reader`]. Pass it to [`Comptainer::iter_added`],
+/// [`Comptainer::iter_changed`] and [`Comptainer::iter_removed`] to get only what happened since
+/// it was last passed to [`Comptainer::update_reader`].
+#[derive(Debug)]
+pub struct ChangeReader {
+    /// Id of the comptainer that created the reader
+    comptainer: u64,
+    id: usize,
+    last_tick: u64,
+    /// Removals before this tick are ignored. Starts at the reader's creation instead of 0, so it
+    /// never sees removals of components it didn't see being added
+    removed_tick: u64,
+}
+
 #[derive(Debug)]
 pub struct Comptainer<T> {
+    /// Unique per comptainer, so readers can't be passed to one they weren't created by
+    id: u64,
     id_to_pos: AHashMap<Entity, usize>,
     comps: Vec<T>,
+    ticks: Vec<ChangeTicks>,
+    change_tick: u64,
+    removed: Vec<(Entity, u64)>,
+    reader_ticks: Vec<Option<u64>>,
 }
 impl<T> Default for Comptainer<T>
 where
@@ -264,8 +339,13 @@ where
 {
     fn default() -> Self {
         Self {
+            id: entity_impl::next_comptainer_id(),
             id_to_pos: AHashMap::default(),
             comps: Vec::default(),
+            ticks: Vec::default(),
+            change_tick: 0,
+            removed: Vec::default(),
+            reader_ticks: Vec::default(),
         }
     }
 }
@@ -280,26 +360,40 @@ where
         Self {
             id_to_pos: AHashMap::with_capacity(cap),
             comps: Vec::with_capacity(cap),
+            ticks: Vec::with_capacity(cap),
+            ..Default::default()
         }
     }
+    fn next_tick(&mut self) -> u64 {
+        self.change_tick += 1;
+        self.change_tick
+    }
+    /// Counter incremented every time components are added, mutably accessed, or removed.
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick
+    }
     pub fn has_component(&self, entity: Entity) -> bool {
         self.id_to_pos.contains_key(&entity)
     }
     pub fn add_component(&mut self, entity: Entity, component: T) -> Option<T> {
+        let tick = self.next_tick();
         match self.id_to_pos.entry(entity) {
             Entry::Occupied(entry) => {
+                self.ticks[*entry.get()].changed = tick;
                 Some(std::mem::replace(&mut self.comps[*entry.get()], component))
             }
             Entry::Vacant(entry) => {
                 entry.insert(self.comps.len());
                 self.comps.push(component);
+                self.ticks.push(ChangeTicks::new(tick));
                 None
             }
         }
     }
     pub fn remove_component(&mut self, entity: Entity) -> bool {
-        if let Some(&i) = self.id_to_pos.get(&entity) {
+        if let Some(i) = self.id_to_pos.remove(&entity) {
             self.comps.swap_remove(i);
+            self.ticks.swap_remove(i);
             if i < self.comps.len() {
                 *self
                     .id_to_pos
@@ -308,6 +402,10 @@ where
                     .unwrap()
                     .1 = i;
             }
+            let tick = self.next_tick();
+            if self.has_readers() {
+                self.removed.push((entity, tick));
+            }
             true
         } else {
             false
@@ -317,10 +415,15 @@ where
         self.id_to_pos.len()
     }
     pub fn take(&mut self) -> (Vec<Entity>, Vec<T>) {
+        let tick = self.next_tick();
         let mut entities = vec![Entity::RESERVED; self.id_to_pos.len()];
         std::mem::take(&mut self.id_to_pos)
             .into_iter()
             .for_each(|(e, pos)| entities[pos] = e);
+        self.ticks.clear();
+        if self.has_readers() {
+            self.removed.extend(entities.iter().map(|&e| (e, tick)));
+        }
 
         (entities, std::mem::take(&mut self.comps))
     }
@@ -331,7 +434,10 @@ where
     pub fn iter(&self) -> CompIter<T> {
         CompIter::new(self)
     }
+    /// Marks every component as changed.
     pub fn iter_mut(&mut self) -> CompIterMut<T> {
+        let tick = self.next_tick();
+        self.ticks.iter_mut().for_each(|t| t.changed = tick);
         CompIterMut::new(self)
     }
     pub fn get(&self, entity: Entity) -> Option<&T> {
@@ -341,8 +447,10 @@ where
             None
         }
     }
+    /// Marks the component as changed.
     pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
         if let Some(&i) = self.id_to_pos.get(&entity) {
+            self.ticks[i].changed = self.next_tick();
             Some(&mut self.comps[i])
         } else {
             None
@@ -355,6 +463,92 @@ where
             None
         }
     }
+    /// Registers a new reader. It will see every component currently in the comptainer as added,
+    /// but only removals from now on.
+    pub fn change_reader(&mut self) -> ChangeReader {
+        let tick = Some(self.change_tick);
+        let id = if let Some(id) = self.reader_ticks.iter().position(Option::is_none) {
+            self.reader_ticks[id] = tick;
+            id
+        } else {
+            self.reader_ticks.push(tick);
+            self.reader_ticks.len() - 1
+        };
+        ChangeReader {
+            comptainer: self.id,
+            id,
+            last_tick: 0,
+            removed_tick: self.change_tick,
+        }
+    }
+    /// Unregisters a reader, so removals are no longer kept around for it.
+    pub fn remove_change_reader(&mut self, reader: ChangeReader) {
+        self.check_reader(&reader);
+        self.reader_ticks[reader.id] = None;
+        self.trim_removed();
+    }
+    /// Marks everything up to now as seen by `reader`.
+    pub fn update_reader(&mut self, reader: &mut ChangeReader) {
+        self.check_reader(reader);
+        reader.last_tick = self.change_tick;
+        reader.removed_tick = self.change_tick;
+        self.reader_ticks[reader.id] = Some(self.change_tick);
+        self.trim_removed();
+    }
+    /// Removals are only kept while there's a reader to see them.
+    fn has_readers(&self) -> bool {
+        self.reader_ticks.iter().any(Option::is_some)
+    }
+    /// Panics if `reader` was created by another comptainer.
+    fn check_reader(&self, reader: &ChangeReader) {
+        assert_eq!(
+            reader.comptainer, self.id,
+            "ChangeReader used with a comptainer it wasn't created by"
+        );
+    }
+    fn trim_removed(&mut self) {
+        let oldest = self.reader_ticks.iter().flatten().min().copied();
+        if let Some(oldest) = oldest {
+            self.removed.retain(|&(_, tick)| tick > oldest);
+        } else {
+            self.removed.clear();
+        }
+    }
+    /// Iterates components added since `reader` was last updated.
+    pub fn iter_added<'a>(
+        &'a self,
+        reader: &ChangeReader,
+    ) -> impl Iterator<Item = (Entity, &'a T)> + 'a {
+        self.check_reader(reader);
+        let last_tick = reader.last_tick;
+        self.id_to_pos
+            .iter()
+            .filter(move |(_, &i)| self.ticks[i].added > last_tick)
+            .map(|(&e, &i)| (e, &self.comps[i]))
+    }
+    /// Iterates components added or mutably accessed since `reader` was last updated.
+    pub fn iter_changed<'a>(
+        &'a self,
+        reader: &ChangeReader,
+    ) -> impl Iterator<Item = (Entity, &'a T)> + 'a {
+        self.check_reader(reader);
+        let last_tick = reader.last_tick;
+        self.id_to_pos
+            .iter()
+            .filter(move |(_, &i)| self.ticks[i].changed > last_tick)
+            .map(|(&e, &i)| (e, &self.comps[i]))
+    }
+    /// Iterates entities whose component was removed since `reader` was last updated.
+    ///
+    /// An entity may still have a component if it was re-added afterwards.
+    pub fn iter_removed<'a>(&'a self, reader: &ChangeReader) -> impl Iterator<Item = Entity> + 'a {
+        self.check_reader(reader);
+        let removed_tick = reader.removed_tick;
+        self.removed
+            .iter()
+            .filter(move |(_, tick)| *tick > removed_tick)
+            .map(|(e, _)| *e)
+    }
 }
 impl<T> Serialize for Comptainer<T>
 where
@@ -416,18 +610,217 @@ where
     }
 }
 
+bitflags! {
+    /// Identifies comptainers in [`Components`], ex. for processes to declare which they access.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CompFlags: u32 {
+        const TRANSFORMS = 1 << 0;
+        const STATIC_MESH_INSTANCES = 1 << 1;
+        const CAMERAS = 1 << 2;
+        const ACTION_HANDLERS = 1 << 3;
+        const PHYSICS_BODIES = 1 << 4;
+        const COLLISION_SHAPES = 1 << 5;
+        const UIS = 1 << 6;
+        const GLOBAL_TRANSFORMS = 1 << 7;
+        const CHILDREN = 1 << 8;
+        const TIMERS = 1 << 9;
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Components {
     pub transforms: RwLock<Comptainer<game::Transform>>,
+    pub global_transforms: RwLock<Comptainer<game::GlobalTransform>>,
+    pub children: RwLock<Comptainer<game::Children>>,
     pub static_mesh_instances: RwLock<Comptainer<render::StaticMeshInstance>>,
     pub cameras: RwLock<Comptainer<render::Camera>>,
     pub action_handlers: RwLock<Comptainer<game::ActionHandler>>,
     pub physics_bodies: RwLock<Comptainer<physics::PhysicsBody>>,
     pub collision_shapes: RwLock<Comptainer<physics::ColliderShape>>,
     pub uis: RwLock<Comptainer<game::UIComponent>>,
+    pub timers: RwLock<Comptainer<clock::Timers>>,
 }
 impl Components {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Removes every component belonging to `entity`.
+    pub fn despawn(&self, entity: Entity) {
+        self.transforms.write().unwrap().remove_component(entity);
+        self.global_transforms
+            .write()
+            .unwrap()
+            .remove_component(entity);
+        self.children.write().unwrap().remove_component(entity);
+        self.static_mesh_instances
+            .write()
+            .unwrap()
+            .remove_component(entity);
+        self.cameras.write().unwrap().remove_component(entity);
+        self.action_handlers
+            .write()
+            .unwrap()
+            .remove_component(entity);
+        self.physics_bodies
+            .write()
+            .unwrap()
+            .remove_component(entity);
+        self.collision_shapes
+            .write()
+            .unwrap()
+            .remove_component(entity);
+        self.uis.write().unwrap().remove_component(entity);
+        self.timers.write().unwrap().remove_component(entity);
+    }
+    /// Removes every component belonging to `entity` and all of its descendants. Children added since
+    /// the last [`TransformProcess`](game::TransformProcess) step are not included.
+    pub fn despawn_recursive(&self, entity: Entity) {
+        let mut to_despawn = vec![entity];
+        while let Some(e) = to_despawn.pop() {
+            if let Some(children) = self.children.read().unwrap().get(e) {
+                to_despawn.extend(children.iter());
+            }
+            self.despawn(e);
+        }
+    }
+}
+
+/// A component with a comptainer in [`Components`], so it can be looked up by type.
+pub trait StoredComponent: Component + Send + Sync + Sized {
+    fn comptainer(components: &Components) -> &RwLock<Comptainer<Self>>;
+}
+macro_rules! stored_components {
+    ($($field:ident: $comp:ty),* $(,)?) => {
+        $(
+            impl StoredComponent for $comp {
+                fn comptainer(components: &Components) -> &RwLock<Comptainer<Self>> {
+                    &components.$field
+                }
+            }
+        )*
+    };
+}
+stored_components!(
+    transforms: game::Transform,
+    global_transforms: game::GlobalTransform,
+    children: game::Children,
+    static_mesh_instances: render::StaticMeshInstance,
+    cameras: render::Camera,
+    action_handlers: game::ActionHandler,
+    physics_bodies: physics::PhysicsBody,
+    collision_shapes: physics::ColliderShape,
+    uis: game::UIComponent,
+    timers: clock::Timers,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Value(u32);
+    impl Component for Value {}
+
+    fn sorted<I: Iterator<Item = Entity>>(iter: I) -> Vec<Entity> {
+        let mut entities: Vec<_> = iter.collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn reader_sees_changes_since_last_update() {
+        let mut comps = Comptainer::new();
+        let [a, b, c] = [Entity::new(), Entity::new(), Entity::new()];
+        comps.add_component(a, Value(0));
+        let mut reader = comps.change_reader();
+        comps.add_component(b, Value(1));
+
+        // Everything present when the reader is created counts as added
+        assert_eq!(
+            sorted(comps.iter_added(&reader).map(|(e, _)| e)),
+            vec![a, b]
+        );
+        comps.update_reader(&mut reader);
+        assert_eq!(comps.iter_added(&reader).count(), 0);
+        assert_eq!(comps.iter_changed(&reader).count(), 0);
+
+        comps.get_mut(a).unwrap().0 = 5;
+        comps.add_component(c, Value(2));
+        comps.remove_component(b);
+        assert_eq!(sorted(comps.iter_added(&reader).map(|(e, _)| e)), vec![c]);
+        assert_eq!(
+            sorted(comps.iter_changed(&reader).map(|(e, _)| e)),
+            vec![a, c]
+        );
+        assert_eq!(comps.iter_removed(&reader).collect::<Vec<_>>(), vec![b]);
+
+        comps.update_reader(&mut reader);
+        assert_eq!(comps.iter_changed(&reader).count(), 0);
+        assert_eq!(comps.iter_removed(&reader).count(), 0);
+    }
+
+    #[test]
+    fn removals_are_trimmed_once_seen() {
+        let mut comps = Comptainer::new();
+        let entities: Vec<_> = (0..3).map(|_| Entity::new()).collect();
+        for (i, e) in entities.iter().enumerate() {
+            comps.add_component(*e, Value(i as u32));
+        }
+        let mut fast = comps.change_reader();
+        let mut slow = comps.change_reader();
+        comps.remove_component(entities[0]);
+        comps.update_reader(&mut fast);
+        comps.remove_component(entities[1]);
+        comps.update_reader(&mut fast);
+        // Kept until the slowest reader has seen them
+        assert_eq!(comps.removed.len(), 2);
+        comps.update_reader(&mut slow);
+        assert!(comps.removed.is_empty());
+
+        comps.remove_change_reader(fast);
+        comps.remove_change_reader(slow);
+        comps.remove_component(entities[2]);
+        comps.take();
+        assert!(comps.removed.is_empty());
+    }
+
+    #[test]
+    fn new_readers_skip_earlier_removals() {
+        let mut comps = Comptainer::new();
+        let [a, b] = [Entity::new(), Entity::new()];
+        comps.add_component(a, Value(0));
+        comps.add_component(b, Value(1));
+        let old = comps.change_reader();
+        comps.remove_component(a);
+        let new = comps.change_reader();
+        assert_eq!(comps.iter_removed(&old).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(comps.iter_removed(&new).count(), 0);
+        assert_eq!(
+            comps.iter_added(&new).map(|(e, _)| e).collect::<Vec<_>>(),
+            vec![b]
+        );
+
+        comps.remove_component(b);
+        assert_eq!(comps.iter_removed(&new).collect::<Vec<_>>(), vec![b]);
+    }
+
+    #[test]
+    fn removals_without_readers_are_not_kept() {
+        let mut comps = Comptainer::new();
+        for _ in 0..16 {
+            let e = Entity::new();
+            comps.add_component(e, Value(0));
+            comps.remove_component(e);
+        }
+        assert!(comps.removed.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "wasn't created by")]
+    fn reader_from_another_comptainer_panics() {
+        let mut comps = Comptainer::<Value>::new();
+        let other = Comptainer::<Value>::new();
+        let reader = comps.change_reader();
+        other.iter_added(&reader).count();
+    }
 }
@@ -1,23 +1,43 @@
 use std::{
-    convert::Infallible,
+    any::TypeId,
     error::Error,
     fmt::{Debug, Display},
     sync::{
         mpsc::{Receiver, Sender},
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
 
+use ahash::{AHashMap, AHashSet};
 use log::{log_enabled, Level};
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
+use winit::event_loop::EventLoopProxy;
 
 use crate::{
-    framework::{Component, Components, Comptainer, Entity},
-    input::{self, Action, ActionFlags, Input},
-    physics::{PhysicsBody, PhysicsProcess},
-    render::{Camera, RenderMessage, StaticMeshInstance, UpdateUI},
-    System, SystemMessage,
+    action::{Action, ActionId, ActionValue},
+    buttons::ButtonStates,
+    clock::{GameTime, TimerProcess},
+    command::Commands,
+    console::{ArgKind, Console, ConsoleCommand, ConsoleCommands, ConsoleErr, ConsoleLog},
+    context::{GAMEPLAY, MENU},
+    cursor::CursorMode,
+    cvar::Cvars,
+    event::{EventReader, EventWriter},
+    framework::{CompFlags, Component, Components, Comptainer, Entity, EntityMap},
+    input::Input,
+    inspector::Inspector,
+    physics::PhysicsProcess,
+    profile::{Profiler, ProfilerOverlay},
+    reflect::impl_reflect,
+    render::{Camera, RenderMessage, UpdateUI},
+    resource::Resources,
+    scene::SceneErr,
+    schedule::{Schedule, ScheduleErr},
+    settings::SettingsPanel,
+    timer::{Stopwatch, Timer},
+    AppMessage, System, SystemMessage,
 };
 
 use hydrolox_pga3d::prelude as pga;
@@ -25,7 +45,14 @@ use hydrolox_pga3d::prelude as pga;
 #[derive(Debug)]
 pub enum GameMessage {
     Stop,
-    Input(input::Action),
+    Input(Action),
+    /// Enables or disables the process with the given name
+    SetProcessEnabled(String, bool),
+    SetPaused(bool),
+    TogglePause,
+    /// Runs a single gameplay step while paused
+    Step,
+    SetTimescale(f32),
 }
 impl SystemMessage for GameMessage {
     fn stop_msg() -> Self {
@@ -45,43 +72,259 @@ impl Transform {
     pub fn new(parent: Option<Entity>, motor: pga::Motor) -> Self {
         Self { parent, motor }
     }
-    pub fn global_motor(&self, transforms: &Comptainer<Transform>) -> pga::Motor {
-        if let Some(parent) = self.parent {
-            transforms
-                .get(parent)
-                .unwrap()
-                .global_motor(transforms)
-                .combine(self.motor)
-        } else {
-            self.motor
+    /// Walks up the hierarchy to find the motor relative to the world. Prefer reading the
+    /// entity's [`GlobalTransform`], which is computed once per step.
+    pub fn global_motor(
+        &self,
+        transforms: &Comptainer<Transform>,
+    ) -> Result<pga::Motor, TransformErr> {
+        let mut motor = self.motor;
+        let mut parent = self.parent;
+        let mut depth = 0;
+        while let Some(entity) = parent {
+            depth += 1;
+            if depth > transforms.len() {
+                return Err(TransformErr::Cycle(entity));
+            }
+            let transform = transforms
+                .get(entity)
+                .ok_or(TransformErr::MissingParent(entity))?;
+            motor = transform.motor.combine(motor);
+            parent = transform.parent;
         }
+        Ok(motor)
     }
-    pub fn parent_motor(&self, transforms: &Comptainer<Transform>) -> Option<pga::Motor> {
-        Some(
-            transforms
-                .get(self.parent?)
-                .unwrap()
-                .global_motor(transforms),
+    pub fn parent_motor(
+        &self,
+        transforms: &Comptainer<Transform>,
+    ) -> Result<Option<pga::Motor>, TransformErr> {
+        let Some(parent) = self.parent else {
+            return Ok(None);
+        };
+        transforms
+            .get(parent)
+            .ok_or(TransformErr::MissingParent(parent))?
+            .global_motor(transforms)
+            .map(Some)
+    }
+}
+impl_reflect!(Transform {
+    parent: Option<Entity>,
+    motor: pga::Motor,
+});
+impl Component for Transform {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.parent = self.parent.map(|parent| map.map(parent));
+    }
+}
+
+#[derive(Debug)]
+pub enum TransformErr {
+    MissingParent(Entity),
+    /// The entity's parent chain loops back on itself
+    Cycle(Entity),
+}
+impl Display for TransformErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingParent(entity) => writeln!(f, "Parent {entity} has no transform"),
+            Self::Cycle(entity) => writeln!(f, "Parent of {entity} is its own ancestor"),
+        }
+    }
+}
+impl Error for TransformErr {}
+
+/// The motor of an entity relative to the world, computed from its [`Transform`] hierarchy once
+/// per step by the [`TransformProcess`]. Not set until the step after the transform is added.
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalTransform {
+    pub motor: pga::Motor,
+}
+impl Component for GlobalTransform {}
+
+/// Entities whose [`Transform`] has this entity as its parent. Maintained by the [`TransformProcess`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+impl Component for Children {}
+
+/// Propagates transforms down the hierarchy into each entity's [`GlobalTransform`] and [`Children`].
+///
+/// Entities whose parent is missing are treated as roots, and are reported once per entity.
+/// Entities in a parent cycle are detached and get no global transform, and neither do their
+/// descendants. Each cycle is reported once.
+#[derive(Debug, Default)]
+pub struct TransformProcess {
+    reported: AHashSet<Entity>,
+}
+impl TransformProcess {
+    fn report(&mut self, entity: Entity, err: TransformErr) {
+        if self.reported.insert(entity) {
+            log::error!("Bad transform hierarchy for entity {entity}: {err}");
+        }
+    }
+    /// Finds the parent cycles among `unreachable`, the entities not descended from any root, and
+    /// reports each new one.
+    fn report_cycles(&mut self, transforms: &Comptainer<Transform>, unreachable: &[Entity]) {
+        let mut checked = AHashSet::with_capacity(unreachable.len());
+        for &start in unreachable {
+            // Follow parents until reaching a checked entity or one already on the path, which
+            // must be on a cycle. Unreachable entities always have a parent with a transform
+            let mut path: AHashMap<Entity, usize> = AHashMap::new();
+            let mut order = Vec::new();
+            let mut current = start;
+            while !checked.contains(&current) && !path.contains_key(&current) {
+                path.insert(current, order.len());
+                order.push(current);
+                current = transforms.get(current).unwrap().parent.unwrap();
+            }
+            checked.extend(order.iter().copied());
+            if let Some(&cycle_start) = path.get(&current) {
+                let members = &order[cycle_start..];
+                let mut new = false;
+                for &member in members {
+                    new |= self.reported.insert(member);
+                }
+                if new {
+                    let members: Vec<String> = members.iter().map(Entity::to_string).collect();
+                    log::error!(
+                        "Bad transform hierarchy: entities {} form a parent cycle and are detached",
+                        members.join(", ")
+                    );
+                }
+            }
+        }
+    }
+}
+impl Process for TransformProcess {
+    fn new(_: &Components, _: &mut Resources, _: &Sender<RenderMessage>) -> Self
+    where
+        Self: Sized,
+    {
+        Self::default()
+    }
+    fn name(&self) -> &'static str {
+        "Transform"
+    }
+    fn stage(&self) -> ProcessStage {
+        ProcessStage::Late
+    }
+    /// Keeps edits made while paused, ex. from the inspector, visible
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::new(
+            CompFlags::TRANSFORMS,
+            CompFlags::GLOBAL_TRANSFORMS | CompFlags::CHILDREN,
         )
     }
+    fn step(&mut self, components: &Components, _: &Resources, _: &mut Commands) {
+        let transforms = components.transforms.read().unwrap();
+        let mut global_transforms = components.global_transforms.write().unwrap();
+        let mut children = components.children.write().unwrap();
+
+        let mut child_lists: AHashMap<Entity, Vec<Entity>> = AHashMap::new();
+        let mut roots = Vec::new();
+        for (e, transform) in transforms.iter() {
+            match transform.parent {
+                Some(parent) if transforms.has_component(parent) => {
+                    child_lists.entry(parent).or_default().push(e)
+                }
+                Some(parent) => {
+                    self.report(e, TransformErr::MissingParent(parent));
+                    roots.push(e);
+                }
+                None => roots.push(e),
+            }
+        }
+
+        let mut visited = AHashSet::with_capacity(transforms.len());
+        let mut to_visit: Vec<(Entity, pga::Motor)> = roots
+            .into_iter()
+            .map(|e| (e, transforms.get(e).unwrap().motor))
+            .collect();
+        while let Some((e, motor)) = to_visit.pop() {
+            visited.insert(e);
+            if let Some(global) = global_transforms.get_mut(e) {
+                global.motor = motor;
+            } else {
+                global_transforms.add_component(e, GlobalTransform { motor });
+            }
+            for &child in child_lists.get(&e).into_iter().flatten() {
+                to_visit.push((child, motor.combine(transforms.get(child).unwrap().motor)));
+            }
+        }
+
+        // Anything not reachable from a root is part of, or descended from, a parent cycle
+        if visited.len() < transforms.len() {
+            let unreachable: Vec<Entity> = transforms
+                .iter()
+                .map(|(e, _)| e)
+                .filter(|e| !visited.contains(e))
+                .collect();
+            self.report_cycles(&transforms, &unreachable);
+        }
+        let stale: Vec<Entity> = global_transforms
+            .iter()
+            .map(|(e, _)| e)
+            .filter(|e| !visited.contains(e))
+            .collect();
+        for e in stale {
+            global_transforms.remove_component(e);
+        }
+
+        let stale: Vec<Entity> = children
+            .iter()
+            .map(|(e, _)| e)
+            .filter(|e| !child_lists.contains_key(e))
+            .collect();
+        for e in stale {
+            children.remove_component(e);
+        }
+        for (e, list) in child_lists {
+            let list = Children(list);
+            if children.get(e) != Some(&list) {
+                children.add_component(e, list);
+            }
+        }
+    }
 }
-impl Component for Transform {}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionHandler {
-    handled_actions: ActionFlags,
+    handled_actions: Vec<ActionId>,
+    /// Input context the actions must reach, or None to take them from any context
+    #[serde(default)]
+    context: Option<String>,
+    /// Actions received since the handler's owner last took them
+    #[serde(skip)]
     action_queue: Vec<Action>,
 }
 impl ActionHandler {
-    pub fn new(handled_actions: ActionFlags) -> Self {
+    pub fn new(handled_actions: impl IntoIterator<Item = ActionId>) -> Self {
         Self {
-            handled_actions,
+            handled_actions: handled_actions.into_iter().collect(),
+            context: None,
             action_queue: Vec::default(),
         }
     }
+    /// Only takes actions while they reach `context`.
+    pub fn with_context(mut self, context: &str) -> Self {
+        self.context = Some(context.into());
+        self
+    }
     /// Returns true if the action was taken, false otherwise
-    fn try_handle_action(&mut self, action: Action) -> bool {
-        if self.handled_actions.contains(action.discriminant()) {
+    fn try_handle_action(&mut self, action: Action, input: &Input) -> bool {
+        let reaches = match &self.context {
+            Some(context) => input.contexts().allows(context, action.id),
+            None => input.contexts().claimed_by(action.id).is_some(),
+        };
+        if reaches && self.handled_actions.contains(&action.id) {
             self.action_queue.push(action);
             true
         } else {
@@ -89,40 +332,286 @@ impl ActionHandler {
         }
     }
 }
+impl_reflect!(ActionHandler {
+    handled_actions: Vec<ActionId>,
+    context: Option<String>;
+    skip action_queue
+});
 impl Component for ActionHandler {}
 
 #[derive(Debug)]
-pub enum GameError {}
+pub enum GameError {
+    ThreadPool(ThreadPoolBuildError),
+    Schedule(ScheduleErr),
+    Scene(SceneErr),
+}
 impl Display for GameError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Game error")
+        match self {
+            Self::ThreadPool(err) => writeln!(f, "Failed to create process thread pool: {err}"),
+            Self::Schedule(err) => writeln!(f, "Failed to schedule processes: {err}"),
+            Self::Scene(err) => writeln!(f, "Failed to load startup scene: {err}"),
+        }
     }
 }
 impl Error for GameError {}
+impl From<ThreadPoolBuildError> for GameError {
+    fn from(value: ThreadPoolBuildError) -> Self {
+        Self::ThreadPool(value)
+    }
+}
+impl From<ScheduleErr> for GameError {
+    fn from(value: ScheduleErr) -> Self {
+        Self::Schedule(value)
+    }
+}
+impl From<SceneErr> for GameError {
+    fn from(value: SceneErr) -> Self {
+        Self::Scene(value)
+    }
+}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessStage {
     Early,
     Normal,
     Physics,
     Late,
 }
+impl ProcessStage {
+    /// All stages, in the order they run each step
+    pub const ALL: [Self; 4] = [Self::Early, Self::Normal, Self::Physics, Self::Late];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Early => "Early",
+            Self::Normal => "Normal",
+            Self::Physics => "Physics",
+            Self::Late => "Late",
+        }
+    }
+}
 impl Default for ProcessStage {
     fn default() -> Self {
         Self::Normal
     }
 }
 
+/// The comptainers and resources a process reads and writes during [`Process::step`].
+///
+/// Event queues don't need to be listed, since [`EventReader`] and [`EventWriter`] never hold their
+/// lock while taking another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentAccess {
+    pub reads: CompFlags,
+    pub writes: CompFlags,
+    /// Resources only read, by the [`TypeId`] of the resource
+    pub resource_reads: Vec<TypeId>,
+    pub resource_writes: Vec<TypeId>,
+    /// Set when the process may write any resource, ex. ones chosen at runtime
+    pub all_resources: bool,
+}
+impl ComponentAccess {
+    pub const ALL: Self = Self {
+        reads: CompFlags::all(),
+        writes: CompFlags::all(),
+        resource_reads: Vec::new(),
+        resource_writes: Vec::new(),
+        all_resources: true,
+    };
+    pub fn new(reads: CompFlags, writes: CompFlags) -> Self {
+        Self {
+            reads,
+            writes,
+            resource_reads: Vec::new(),
+            resource_writes: Vec::new(),
+            all_resources: false,
+        }
+    }
+    pub fn reads_resource<T: 'static>(mut self) -> Self {
+        self.resource_reads.push(TypeId::of::<T>());
+        self
+    }
+    pub fn writes_resource<T: 'static>(mut self) -> Self {
+        self.resource_writes.push(TypeId::of::<T>());
+        self
+    }
+    pub fn writes_all_resources(mut self) -> Self {
+        self.all_resources = true;
+        self
+    }
+    fn uses_resources(&self) -> bool {
+        self.all_resources || !self.resource_reads.is_empty() || !self.resource_writes.is_empty()
+    }
+    fn writes_any_resource_of(&self, other: &Self) -> bool {
+        other
+            .resource_reads
+            .iter()
+            .chain(&other.resource_writes)
+            .any(|id| self.resource_writes.contains(id))
+    }
+    /// Returns true if the two can't safely run at the same time.
+    pub fn conflicts(&self, other: &Self) -> bool {
+        self.writes.intersects(other.reads | other.writes)
+            || other.writes.intersects(self.reads | self.writes)
+            || (self.all_resources && other.uses_resources())
+            || (other.all_resources && self.uses_resources())
+            || self.writes_any_resource_of(other)
+            || other.writes_any_resource_of(self)
+    }
+}
+
 pub trait Process: Send + Debug {
-    fn new(components: &Components, render_sender: &Sender<RenderMessage>) -> Self
+    /// Called once when the process is added. Any resources the process needs should be inserted here.
+    fn new(
+        components: &Components,
+        resources: &mut Resources,
+        render_sender: &Sender<RenderMessage>,
+    ) -> Self
     where
         Self: Sized;
-    fn step(&mut self, components: &Components, input: &RwLock<Input>);
+    /// Changes that can't be made while other processes may be running, such as spawning and
+    /// despawning entities, should be recorded in `commands` instead.
+    fn step(&mut self, components: &Components, resources: &Resources, commands: &mut Commands);
+    /// Unique name, used for ordering constraints and to enable or disable the process at runtime
+    fn name(&self) -> &'static str;
+    /// Extra names other processes can order themselves against, shared by every process with the label
+    fn labels(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Processes or labels this process must step before, within the same stage
+    fn before(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Processes or labels this process must step after, within the same stage
+    fn after(&self) -> &'static [&'static str] {
+        &[]
+    }
     fn stage(&self) -> ProcessStage {
         ProcessStage::default()
     }
+    /// Whether the process keeps stepping while gameplay is paused, ex. for UI
+    fn runs_while_paused(&self) -> bool {
+        false
+    }
+    /// Which comptainers and resources the process uses. Processes that don't conflict may step
+    /// concurrently, so every resource locked in [`Process::step`] must be listed.
+    ///
+    /// Defaults to writing everything, which never runs alongside other processes.
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::ALL
+    }
+}
+
+/// Most gameplay steps run per loop when the timescale is above 1
+const MAX_STEPS_PER_LOOP: u32 = 10;
+
+/// How the game loop advances gameplay, stored as a resource.
+#[derive(Debug, Clone, Copy)]
+pub struct Simulation {
+    /// Only processes that run while paused are stepped
+    pub paused: bool,
+    /// How many gameplay steps run per step period of real time
+    pub timescale: f32,
+    /// Gameplay steps still to run while paused, one per loop
+    pub queued_steps: u32,
+}
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            timescale: 1.0,
+            queued_steps: 0,
+        }
+    }
+}
+impl Simulation {
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            log::info!("{}", if paused { "Paused" } else { "Resumed" });
+        }
+        self.paused = paused;
+        self.queued_steps = 0;
+    }
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused);
+    }
+    /// Queues `steps` gameplay steps, pausing first if needed.
+    pub fn step(&mut self, steps: u32) {
+        self.set_paused(true);
+        self.queued_steps = steps;
+    }
+    /// Returns false if `timescale` is outside of `0..=10`.
+    pub fn set_timescale(&mut self, timescale: f32) -> bool {
+        if (0.0..=MAX_STEPS_PER_LOOP as f32).contains(&timescale) {
+            self.timescale = timescale;
+            true
+        } else {
+            false
+        }
+    }
 }
 
-pub const STEP_PERIOD: f32 = 1.0 / 60.0;
+const GAME_COMMANDS: &[ConsoleCommand] = &[
+    ConsoleCommand {
+        name: "pause",
+        usage: "",
+        help: "Pauses or resumes gameplay",
+        args: &[],
+        run: |_, _, resources| {
+            let mut sim = resources.resource_mut::<Simulation>().unwrap();
+            sim.toggle_pause();
+            Ok(if sim.paused { "Paused" } else { "Resumed" }.into())
+        },
+    },
+    ConsoleCommand {
+        name: "step",
+        usage: "[steps]",
+        help: "Pauses and advances gameplay by one or more steps",
+        args: &[ArgKind::Other],
+        run: |args, _, resources| {
+            let steps = match args {
+                [] => 1,
+                [steps] => steps
+                    .parse()
+                    .map_err(|_| ConsoleErr::BadArg(steps.to_string()))?,
+                _ => {
+                    return Err(ConsoleErr::Usage {
+                        name: "step",
+                        usage: "[steps]",
+                    })
+                }
+            };
+            resources.resource_mut::<Simulation>().unwrap().step(steps);
+            Ok(format!("Stepping {steps} steps"))
+        },
+    },
+    ConsoleCommand {
+        name: "timescale",
+        usage: "<scale>",
+        help: "Sets how fast gameplay runs relative to real time, up to 10",
+        args: &[],
+        run: |args, _, resources| {
+            let [scale] = args else {
+                return Err(ConsoleErr::Usage {
+                    name: "timescale",
+                    usage: "<scale>",
+                });
+            };
+            let timescale = scale
+                .parse::<f32>()
+                .map_err(|_| ConsoleErr::BadArg(scale.to_string()))?;
+            if !resources
+                .resource_mut::<Simulation>()
+                .unwrap()
+                .set_timescale(timescale)
+            {
+                return Err(ConsoleErr::BadArg(scale.to_string()));
+            }
+            Ok(format!("Timescale set to {timescale}"))
+        },
+    },
+];
 
 #[derive(Debug)]
 struct Player {
@@ -130,9 +619,12 @@ struct Player {
     move_speed: f32,
     look_speed: f32,
     look_rot: (f32, f32),
+    look_action: Option<ActionId>,
+    move_action: Option<ActionId>,
+    button_clicks: EventReader<ButtonClicked>,
 }
 impl Process for Player {
-    fn new(components: &Components, _: &Sender<RenderMessage>) -> Self
+    fn new(components: &Components, resources: &mut Resources, _: &Sender<RenderMessage>) -> Self
     where
         Self: Sized,
     {
@@ -149,19 +641,46 @@ impl Process for Player {
                 near_plane: 0.1,
             },
         );
+        let input = resources.resource::<Input>().unwrap();
+        let look_action = input.action_id("Look");
+        let move_action = input.action_id("Move");
+        drop(input);
         components.action_handlers.write().unwrap().add_component(
             player_entity,
-            ActionHandler::new(Action::Look(0.0, 0.0).discriminant()),
+            ActionHandler::new(look_action).with_context(GAMEPLAY),
         );
+        if let Some(mut cvars) = resources.resource_mut::<Cvars>() {
+            cvars.register("player.move_speed", 3.0f32, "Player movement speed in m/s");
+            cvars.register(
+                "player.look_speed",
+                0.3f32,
+                "Mouse look sensitivity in radians per pixel per second",
+            );
+        }
 
         Self {
             player_entity,
             move_speed: 3.0,
             look_speed: 0.3,
             look_rot: (0.0, 0.0),
+            look_action,
+            move_action,
+            button_clicks: EventReader::new(resources),
         }
     }
-    fn step(&mut self, components: &Components, input: &RwLock<Input>) {
+    fn name(&self) -> &'static str {
+        "Player"
+    }
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::new(
+            CompFlags::empty(),
+            CompFlags::TRANSFORMS | CompFlags::ACTION_HANDLERS,
+        )
+        .reads_resource::<GameTime>()
+        .reads_resource::<Cvars>()
+        .reads_resource::<Input>()
+    }
+    fn step(&mut self, components: &Components, resources: &Resources, _: &mut Commands) {
         let actions = std::mem::take(
             &mut components
                 .action_handlers
@@ -172,37 +691,69 @@ impl Process for Player {
                 .action_queue,
         );
 
+        let dt = resources.resource::<GameTime>().unwrap().delta;
+        if let Some(cvars) = resources.resource::<Cvars>() {
+            self.move_speed = cvars.get("player.move_speed").unwrap_or(self.move_speed);
+            self.look_speed = cvars.get("player.look_speed").unwrap_or(self.look_speed);
+        }
+
+        let mut reset = false;
+        self.button_clicks.read(|ButtonClicked(button)| {
+            reset |= *button == RESET_PLAYER_BUTTON;
+        });
+        if reset {
+            self.look_rot = (0.0, 0.0);
+            components
+                .transforms
+                .write()
+                .unwrap()
+                .get_mut(self.player_entity)
+                .unwrap()
+                .motor = pga::Motor::IDENTITY;
+        }
+
+        let input = resources.resource::<Input>().unwrap();
+        // Mouse motion arrives as actions, while sticks are polled every step
+        let poll = |id: Option<ActionId>| match id {
+            Some(id) if input.contexts().allows(GAMEPLAY, id) => input.axis2d(id),
+            _ => (0.0, 0.0),
+        };
+        let mut look_amt = poll(self.look_action);
+        let move_amt = poll(self.move_action);
+        drop(input);
         for action in actions {
-            match action {
-                Action::Look(x, y) => {
-                    self.look_rot.0 += x * self.look_speed * STEP_PERIOD;
-                    self.look_rot.1 += y * self.look_speed * STEP_PERIOD;
-                    self.look_rot.1 = self
-                        .look_rot
-                        .1
-                        .clamp((-80.0f32).to_radians(), (80.0f32).to_radians());
-
-                    let mut transforms = components.transforms.write().unwrap();
-                    let motor = &mut transforms.get_mut(self.player_entity).unwrap().motor;
-
-                    let translation = motor.factor_translation();
-                    let rotation =
-                        pga::Motor::from_euler_angles(-self.look_rot.1, -self.look_rot.0, 0.0);
-                    *motor = rotation.combine(translation);
+            match action.value {
+                ActionValue::Axis2d(x, y) if Some(action.id) == self.look_action => {
+                    look_amt.0 += x;
+                    look_amt.1 += y;
                 }
                 _ => (), //panic!("Player got unknown action"),
             }
         }
 
-        let move_amt = input.read().unwrap().query_move();
+        if look_amt.0 != 0.0 || look_amt.1 != 0.0 {
+            self.look_rot.0 += look_amt.0 * self.look_speed * dt;
+            self.look_rot.1 += look_amt.1 * self.look_speed * dt;
+            self.look_rot.1 = self
+                .look_rot
+                .1
+                .clamp((-80.0f32).to_radians(), (80.0f32).to_radians());
+
+            let mut transforms = components.transforms.write().unwrap();
+            let motor = &mut transforms.get_mut(self.player_entity).unwrap().motor;
+
+            let translation = motor.factor_translation();
+            let rotation = pga::Motor::from_euler_angles(-self.look_rot.1, -self.look_rot.0, 0.0);
+            *motor = rotation.combine(translation);
+        }
         if move_amt.0 != 0.0 || move_amt.1 != 0.0 {
             let mut transforms = components.transforms.write().unwrap();
             let motor = &mut transforms.get_mut(self.player_entity).unwrap().motor;
 
             let to_move = motor.factor_rotation().transform(pga::Point::from_position(
-                move_amt.0 * self.move_speed * STEP_PERIOD,
+                move_amt.0 * self.move_speed * dt,
                 0.0,
-                -move_amt.1 * self.move_speed * STEP_PERIOD,
+                -move_amt.1 * self.move_speed * dt,
             ));
             *motor = motor.combine(pga::Motor::from_translation(
                 to_move.x, to_move.y, to_move.z,
@@ -213,49 +764,205 @@ impl Process for Player {
 
 pub trait UIElement: Send + Sync + Debug {
     fn active(&self) -> bool;
-    fn show(&mut self, ctx: &egui::Context);
+    /// Components can be read directly, but changes to them should go through `commands`.
+    fn show(&mut self, ctx: &egui::Context, components: &Components, commands: &mut Commands);
+    /// Receives actions sent to an [`ActionHandler`] on the same entity, even while inactive.
+    fn handle_action(&mut self, _action: Action) {}
+    /// Input context pushed while the element is active.
+    fn input_context(&self) -> Option<&'static str> {
+        None
+    }
 }
 
+/// Elements are shared so they can be shown without the UI comptainer locked, letting them read
+/// any component.
 #[derive(Debug)]
 pub struct UIComponent {
-    element: Box<dyn UIElement>,
+    element: Arc<Mutex<dyn UIElement>>,
+}
+impl UIComponent {
+    pub fn new(element: impl UIElement + 'static) -> Self {
+        Self {
+            element: Arc::new(Mutex::new(element)),
+        }
+    }
 }
 impl Component for UIComponent {}
 
+/// How often `binds.yaml` is checked for changes
+const BINDS_POLL_PERIOD: Duration = Duration::from_secs(1);
+
+/// Reloads `binds.yaml` when it changes, so bindings can be edited without restarting.
+#[derive(Debug)]
+struct BindsWatcher {
+    input: Arc<RwLock<Input>>,
+    poll_timer: Timer,
+}
+impl Process for BindsWatcher {
+    fn new(_: &Components, resources: &mut Resources, _: &Sender<RenderMessage>) -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            input: resources.shared().unwrap(),
+            poll_timer: Timer::from_now(BINDS_POLL_PERIOD),
+        }
+    }
+    fn name(&self) -> &'static str {
+        "BindsWatcher"
+    }
+    fn stage(&self) -> ProcessStage {
+        ProcessStage::Early
+    }
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::new(CompFlags::empty(), CompFlags::empty()).writes_resource::<Input>()
+    }
+    fn step(&mut self, _: &Components, _: &Resources, _: &mut Commands) {
+        if self.poll_timer.check_reset() {
+            Input::reload_changed_bindings(&self.input);
+        }
+    }
+}
+
 #[derive(Debug)]
 struct UI {
     ctx: egui::Context,
     start_time: Instant,
     render_sender: Sender<RenderMessage>,
+    /// None when running headless
+    app_proxy: Option<EventLoopProxy<AppMessage>>,
+    /// Whether the cursor should be locked for mouselook whenever gameplay can use `Look`
+    mouselook: bool,
+    look_action: Option<ActionId>,
+    toggle_cursor_action: Option<ActionId>,
+    /// Text cursor of the focused text field in physical pixels, where the OS shows IME candidates
+    ime_area: Option<egui::Rect>,
+}
+impl UI {
+    /// Sends `msg` to the app, unless running headless.
+    fn send_to_app(&self, msg: AppMessage) {
+        if let Some(proxy) = &self.app_proxy {
+            if let Err(err) = proxy.send_event(msg) {
+                log::error!("Failed to send {:?} to app: {err}", err.0);
+            }
+        }
+    }
+    /// Locks the cursor for mouselook while gameplay has `Look` and frees it for UI otherwise.
+    /// `ToggleCursor` switches mouselook off and on, and while it's off, clicking anywhere egui
+    /// doesn't want the pointer switches it back on.
+    fn update_cursor_mode(&mut self, resources: &Resources) {
+        let mut input = resources.resource_mut::<Input>().unwrap();
+        let buttons = resources.resource::<ButtonStates>().unwrap();
+        if self
+            .toggle_cursor_action
+            .is_some_and(|id| buttons.just_pressed(id))
+        {
+            self.mouselook = !self.mouselook;
+        } else if input.cursor_mode() == CursorMode::Free
+            && !self.ctx.wants_pointer_input()
+            && self.ctx.input(|i| i.pointer.primary_pressed())
+        {
+            self.mouselook = true;
+        }
+        let look_allowed = self
+            .look_action
+            .is_some_and(|id| input.contexts().claimed_by(id).is_some());
+        let mode = if self.mouselook && look_allowed {
+            CursorMode::Locked
+        } else {
+            CursorMode::Free
+        };
+        if mode != input.cursor_mode() {
+            input.set_cursor_mode(mode);
+            self.send_to_app(AppMessage::SetCursorMode(mode));
+        }
+    }
 }
 impl Process for UI {
-    fn new(_: &Components, render_sender: &Sender<RenderMessage>) -> Self
+    fn new(_: &Components, resources: &mut Resources, render_sender: &Sender<RenderMessage>) -> Self
     where
         Self: Sized,
     {
+        let input = resources.resource::<Input>().unwrap();
         Self {
             ctx: egui::Context::default(),
             start_time: Instant::now(),
             render_sender: render_sender.clone(),
+            app_proxy: resources
+                .resource::<EventLoopProxy<AppMessage>>()
+                .map(|proxy| proxy.clone()),
+            mouselook: true,
+            look_action: input.action_id("Look"),
+            toggle_cursor_action: input.action_id("ToggleCursor"),
+            ime_area: None,
         }
     }
+    fn name(&self) -> &'static str {
+        "UI"
+    }
     fn stage(&self) -> ProcessStage {
         ProcessStage::Late
     }
-    fn step(&mut self, components: &Components, input: &RwLock<Input>) {
-        let mut input = input.write().unwrap();
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+    fn access(&self) -> ComponentAccess {
+        // UI elements can lock whatever resources they hold onto
+        ComponentAccess::new(
+            CompFlags::all(),
+            CompFlags::UIS | CompFlags::ACTION_HANDLERS,
+        )
+        .writes_all_resources()
+    }
+    fn step(&mut self, components: &Components, resources: &Resources, commands: &mut Commands) {
+        let mut input = resources.resource_mut::<Input>().unwrap();
         let raw_input = egui::RawInput {
             time: Some(Instant::now().duration_since(self.start_time).as_secs_f64()),
-            predicted_dt: STEP_PERIOD,
+            predicted_dt: resources.resource::<GameTime>().unwrap().step_period,
             modifiers: input.egui_modifiers(),
             events: input.egui_events(),
+            focused: input.is_focused(),
             ..Default::default()
         };
-        let mut ui_comps = components.uis.write().unwrap();
+        drop(input);
+        let elements: Vec<_> = {
+            let ui_comps = components.uis.read().unwrap();
+            let mut action_handlers = components.action_handlers.write().unwrap();
+            ui_comps
+                .iter()
+                .map(|(e, comp)| {
+                    if let Some(handler) = action_handlers.get_mut(e) {
+                        let mut element = comp.element.lock().unwrap();
+                        for action in std::mem::take(&mut handler.action_queue) {
+                            element.handle_action(action);
+                        }
+                    }
+                    comp.element.clone()
+                })
+                .collect()
+        };
+        {
+            let mut input = resources.resource_mut::<Input>().unwrap();
+            let mut wanted = AHashMap::<&str, bool>::new();
+            for element in &elements {
+                let element = element.lock().unwrap();
+                if let Some(context) = element.input_context() {
+                    *wanted.entry(context).or_default() |= element.active();
+                }
+            }
+            for (context, active) in wanted {
+                input.contexts_mut().set_active(context, active);
+            }
+        }
+        // The UI comptainer isn't locked while elements are shown, so they can read it
         let full_output = self.ctx.run(raw_input, |ctx| {
-            for (_e, comp) in ui_comps.iter_mut() {
-                if comp.element.active() {
-                    comp.element.show(ctx);
+            for element in &elements {
+                let mut element = element.lock().unwrap();
+                if element.active() {
+                    element.show(ctx, components, commands);
                 }
             }
         });
@@ -266,167 +973,448 @@ impl Process for UI {
                 .tessellate(full_output.shapes, full_output.pixels_per_point),
         };
         let _ = self.render_sender.send(RenderMessage::UpdateUI(ui_update));
+        self.update_cursor_mode(resources);
+        // IME is only enabled while a text field has focus, so it doesn't swallow gameplay keys
+        // egui works in points, while the window takes physical pixels
+        let ime_area = full_output
+            .platform_output
+            .ime
+            .map(|ime| ime.cursor_rect * full_output.pixels_per_point);
+        if ime_area != self.ime_area {
+            self.ime_area = ime_area;
+            self.send_to_app(AppMessage::SetImeArea(ime_area));
+        }
     }
 }
 
+/// Sent when a button in the UI is clicked, with the button's label
+#[derive(Debug, Clone)]
+pub struct ButtonClicked(pub &'static str);
+
+/// Moves the player back to the origin, looking straight ahead
+const RESET_PLAYER_BUTTON: &str = "Reset Player";
+
 #[derive(Debug)]
-struct TestUI {}
+struct TestUI {
+    button_clicks: EventWriter<ButtonClicked>,
+}
 impl UIElement for TestUI {
     fn active(&self) -> bool {
         true
     }
-    fn show(&mut self, ctx: &egui::Context) {
+    fn show(&mut self, ctx: &egui::Context, _: &Components, _: &mut Commands) {
         egui::Window::new("UI Window")
             .resizable(false)
             .show(ctx, |ui| {
                 ui.label("It works!");
-                if ui.button("Cool Button").clicked() {
-                    log::info!("egui button clicked!")
+                if ui.button(RESET_PLAYER_BUTTON).clicked() {
+                    self.button_clicks.send(ButtonClicked(RESET_PLAYER_BUTTON));
                 }
             });
     }
 }
 
+const STARTUP_SCENE: &str = "startup";
+
+pub struct GameInit {
+    pub render_sender: Sender<RenderMessage>,
+    /// Step processes one at a time in a fixed order instead of on a thread pool, for debugging
+    pub serial_processes: bool,
+    /// Length of a gameplay step in seconds
+    pub step_period: f32,
+    pub profiler: Arc<RwLock<Profiler>>,
+    /// Used to change the window's cursor mode. None when running headless
+    pub app_proxy: Option<EventLoopProxy<AppMessage>>,
+}
+
 #[derive(Debug)]
 pub struct Game {
     receiver: Receiver<GameMessage>,
     components: Arc<Components>,
     render_sender: Sender<RenderMessage>,
-    input: Arc<RwLock<Input>>,
-    early_processes: Vec<Box<dyn Process>>,
-    normal_processes: Vec<Box<dyn Process>>,
-    physics_processes: Vec<Box<dyn Process>>,
-    late_processes: Vec<Box<dyn Process>>,
+    resources: Resources,
+    processes: Vec<Box<dyn Process>>,
+    schedule: Schedule,
+    thread_pool: Option<ThreadPool>,
+    profiler: Arc<RwLock<Profiler>>,
+    step_period: f32,
+    /// Gameplay steps owed to the timescale, carried over between loops
+    step_debt: f32,
+    /// Pause state the menu context was last set for
     paused: bool,
+    pause_action: Option<ActionId>,
+    step_action: Option<ActionId>,
 }
 impl Game {
     fn add_process<P: Process + 'static>(&mut self) {
-        let process = P::new(&self.components, &self.render_sender);
-        match process.stage() {
-            ProcessStage::Early => self.early_processes.push(Box::new(process)),
-            ProcessStage::Normal => self.normal_processes.push(Box::new(process)),
-            ProcessStage::Physics => self.physics_processes.push(Box::new(process)),
-            ProcessStage::Late => self.late_processes.push(Box::new(process)),
+        self.processes.push(Box::new(P::new(
+            &self.components,
+            &mut self.resources,
+            &self.render_sender,
+        )));
+    }
+    /// Sorts the added processes into the schedule, according to their stages and ordering constraints.
+    fn build_schedule(&mut self) -> Result<(), GameError> {
+        self.schedule = Schedule::new(std::mem::take(&mut self.processes))?;
+        Ok(())
+    }
+    /// Handles every message except [`GameMessage::Stop`], which ends the game loop.
+    fn handle_message(&mut self, msg: GameMessage) {
+        let msg = match msg {
+            GameMessage::Input(action)
+                if action.value == ActionValue::Pressed
+                    && [self.pause_action, self.step_action].contains(&Some(action.id)) =>
+            {
+                // Blocked like any other action, ex. while the console is open
+                let input = self.resources.resource::<Input>().unwrap();
+                if input.contexts().claimed_by(action.id).is_none() {
+                    return;
+                }
+                if Some(action.id) == self.pause_action {
+                    GameMessage::TogglePause
+                } else {
+                    GameMessage::Step
+                }
+            }
+            msg => msg,
+        };
+        match msg {
+            GameMessage::Stop => (),
+            GameMessage::TogglePause => {
+                self.resources
+                    .resource_mut::<Simulation>()
+                    .unwrap()
+                    .toggle_pause();
+            }
+            GameMessage::Step => {
+                self.resources.resource_mut::<Simulation>().unwrap().step(1);
+            }
+            GameMessage::Input(action) => {
+                let input = self.resources.resource::<Input>().unwrap();
+                for (_e, handler) in self.components.action_handlers.write().unwrap().iter_mut() {
+                    if handler.try_handle_action(action, &input) {
+                        break;
+                    }
+                }
+            }
+            GameMessage::SetProcessEnabled(name, enabled) => {
+                if !self.schedule.set_enabled(&name, enabled) {
+                    log::warn!("Can't set enabled state of unknown process \"{name}\"");
+                }
+            }
+            GameMessage::SetPaused(paused) => {
+                self.resources
+                    .resource_mut::<Simulation>()
+                    .unwrap()
+                    .set_paused(paused);
+            }
+            GameMessage::SetTimescale(timescale) => {
+                if !self
+                    .resources
+                    .resource_mut::<Simulation>()
+                    .unwrap()
+                    .set_timescale(timescale)
+                {
+                    log::warn!("Timescale {timescale} is out of range");
+                }
+            }
+        }
+    }
+    /// Runs one iteration of the game loop without waiting for it to be due: handles the
+    /// messages received since the last one, then runs however many steps are owed. Returns false
+    /// once told to stop.
+    ///
+    /// `real_elapsed` is the real time since the game loop started.
+    pub fn run_loop(&mut self, real_elapsed: Duration) -> bool {
+        let loop_start = Instant::now();
+        let msgs: Vec<GameMessage> = self.receiver.try_iter().collect();
+        for msg in msgs {
+            if let GameMessage::Stop = msg {
+                return false;
+            }
+            self.handle_message(msg);
+        }
+
+        let mut steps = 0;
+        let paused = {
+            let mut sim = self.resources.resource_mut::<Simulation>().unwrap();
+            if !sim.paused {
+                self.step_debt += sim.timescale;
+                steps = self.step_debt as u32;
+                self.step_debt -= steps as f32;
+            } else if sim.queued_steps > 0 {
+                sim.queued_steps -= 1;
+                steps = 1;
+            }
+            sim.paused
+        };
+        // Gameplay actions are blocked while paused. Only changed when pausing or unpausing, so
+        // the console's context command can still push or pop the menu
+        if paused != self.paused {
+            self.paused = paused;
+            self.resources
+                .resource_mut::<Input>()
+                .unwrap()
+                .contexts_mut()
+                .set_active(MENU, paused);
         }
+
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.start();
+        // With no gameplay steps due, step once for the processes that run while paused
+        for _ in 0..steps.clamp(1, MAX_STEPS_PER_LOOP) {
+            self.resources
+                .resource_mut::<GameTime>()
+                .unwrap()
+                .advance(real_elapsed, steps == 0);
+            // Recorded input is fed here rather than on receipt, so playback lands on the same steps
+            let actions = self
+                .resources
+                .resource_mut::<Input>()
+                .unwrap()
+                .advance_step();
+            for action in actions {
+                self.handle_message(GameMessage::Input(action));
+            }
+            self.resources
+                .resource_mut::<ButtonStates>()
+                .unwrap()
+                .update(
+                    &self.resources.resource::<GameTime>().unwrap(),
+                    &mut self.resources.resource_mut::<Input>().unwrap(),
+                );
+            self.schedule.run(
+                self.thread_pool.as_ref(),
+                &self.components,
+                &mut self.resources,
+                steps == 0,
+            );
+            self.resources.run_step_hooks();
+        }
+        self.profiler
+            .write()
+            .unwrap()
+            .record("game", "Step", loop_start, stopwatch.total());
+        true
+    }
+    /// Returns false if there is no process called `name`.
+    pub fn set_process_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.schedule.set_enabled(name, enabled)
     }
 }
 impl System for Game {
-    type Init = Sender<RenderMessage>;
-    type InitErr = Infallible;
+    type Init = GameInit;
+    type InitErr = GameError;
     type Err = GameError;
     type Msg = GameMessage;
 
     fn new(
         components: &Arc<Components>,
         input: &Arc<RwLock<Input>>,
-        render_sender: Sender<RenderMessage>,
+        GameInit {
+            render_sender,
+            serial_processes,
+            step_period,
+            profiler,
+            app_proxy,
+        }: GameInit,
         receiver: Receiver<GameMessage>,
-    ) -> Result<Self, Infallible> {
+    ) -> Result<Self, GameError> {
+        let thread_pool = if serial_processes {
+            None
+        } else {
+            Some(
+                ThreadPoolBuilder::new()
+                    .thread_name(|i| format!("Game process worker {i}"))
+                    .build()?,
+            )
+        };
+
+        let mut resources = Resources::new();
+        resources.insert_shared(input.clone());
+        resources.insert_shared(profiler.clone());
+        resources.insert_resource(Simulation::default());
+        resources.insert_resource(GameTime::new(step_period));
+        resources.insert_resource(ButtonStates::new());
+        if let Some(app_proxy) = app_proxy {
+            resources.insert_resource(app_proxy);
+        }
+        resources.insert_resource(Cvars::load());
+        resources.insert_resource(ConsoleLog::default());
+        let mut console_commands = ConsoleCommands::with_builtins();
+        for command in GAME_COMMANDS {
+            console_commands.register(*command);
+        }
+        resources.insert_resource(console_commands);
+
         let mut me = Self {
             receiver,
             components: components.clone(),
-            render_sender: render_sender,
-            input: input.clone(),
-            early_processes: Vec::default(),
-            normal_processes: Vec::default(),
-            physics_processes: Vec::default(),
-            late_processes: Vec::default(),
+            render_sender,
+            resources,
+            processes: Vec::default(),
+            schedule: Schedule::default(),
+            thread_pool,
+            profiler,
+            step_period,
+            step_debt: 0.0,
             paused: false,
+            pause_action: input.read().unwrap().action_id("Pause"),
+            step_action: input.read().unwrap().action_id("Step"),
         };
 
+        let entities = components.load_scene(STARTUP_SCENE)?;
+        log::info!("Loaded startup scene with {} entities", entities.len());
+
+        me.add_process::<TimerProcess>();
+        me.add_process::<BindsWatcher>();
         me.add_process::<PhysicsProcess>();
         me.add_process::<UI>();
         me.add_process::<Player>();
+        me.add_process::<TransformProcess>();
+        me.build_schedule()?;
 
         components.uis.write().unwrap().add_component(
             Entity::new(),
-            UIComponent {
-                element: Box::new(TestUI {}),
-            },
+            UIComponent::new(TestUI {
+                button_clicks: EventWriter::new(&mut me.resources),
+            }),
         );
-
-        let make_monkey = |x: f32, y: f32, z: f32| {
-            let monkey_entity = Entity::new();
-
-            components.transforms.write().unwrap().add_component(
-                monkey_entity,
-                Transform::new(None, pga::Motor::from_translation(x, y, z)),
-            );
-            components
-                .static_mesh_instances
-                .write()
-                .unwrap()
-                .add_component(
-                    monkey_entity,
-                    StaticMeshInstance::new("monkey".into(), "slate_gray".into()),
-                );
-            let mut phys_body = PhysicsBody::default();
-            phys_body.angular_velocity.y = 180.0f32.to_radians();
-            components
-                .physics_bodies
-                .write()
-                .unwrap()
-                .add_component(monkey_entity, phys_body);
-        };
-
-        make_monkey(2.0, 0.0, -2.0);
-        make_monkey(0.0, 0.0, -2.0);
-        make_monkey(-2.0, 0.0, 0.0);
+        let [toggle_inspector, toggle_profiler, toggle_console, toggle_settings] = [
+            "ToggleInspector",
+            "ToggleProfiler",
+            "ToggleConsole",
+            "ToggleSettings",
+        ]
+        .map(|name| input.read().unwrap().action_id(name));
+        let inspector = Entity::new();
+        components
+            .uis
+            .write()
+            .unwrap()
+            .add_component(inspector, UIComponent::new(Inspector::new()));
+        components
+            .action_handlers
+            .write()
+            .unwrap()
+            .add_component(inspector, ActionHandler::new(toggle_inspector));
+        let profiler_overlay = Entity::new();
+        components.uis.write().unwrap().add_component(
+            profiler_overlay,
+            UIComponent::new(ProfilerOverlay::new(me.profiler.clone(), step_period)),
+        );
+        components
+            .action_handlers
+            .write()
+            .unwrap()
+            .add_component(profiler_overlay, ActionHandler::new(toggle_profiler));
+        let console = Entity::new();
+        components
+            .uis
+            .write()
+            .unwrap()
+            .add_component(console, UIComponent::new(Console::new(&me.resources)));
+        components
+            .action_handlers
+            .write()
+            .unwrap()
+            .add_component(console, ActionHandler::new(toggle_console));
+        let settings = Entity::new();
+        components.uis.write().unwrap().add_component(
+            settings,
+            UIComponent::new(SettingsPanel::new(&me.resources)),
+        );
+        components
+            .action_handlers
+            .write()
+            .unwrap()
+            .add_component(settings, ActionHandler::new(toggle_settings));
 
         Ok(me)
     }
     fn run(&mut self) -> Result<(), GameError> {
-        let mut last_loop_start = Instant::now();
+        let start_time = Instant::now();
+        let mut last_loop_start = start_time;
         let mut next_time = last_loop_start;
         loop {
-            for msg in self.receiver.try_iter() {
-                match msg {
-                    GameMessage::Stop => return Ok(()),
-                    GameMessage::Input(action) => {
-                        if !self.paused {
-                            for (_, handler) in
-                                self.components.action_handlers.write().unwrap().iter_mut()
-                            {
-                                if handler.try_handle_action(action) {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
             if log_enabled!(Level::Trace) {
                 let elapsed = Instant::now().duration_since(last_loop_start).as_secs_f32();
                 log::trace!(
                     "Game loop took: {}ms, {}% work",
                     elapsed * 1000.0,
-                    elapsed / STEP_PERIOD * 100.0
+                    elapsed / self.step_period * 100.0
                 );
             }
             if let Some(to_sleep) = next_time.checked_duration_since(Instant::now()) {
                 std::thread::sleep(to_sleep);
             }
             last_loop_start = Instant::now();
-            next_time = last_loop_start + Duration::from_secs_f32(STEP_PERIOD);
+            next_time = last_loop_start + Duration::from_secs_f32(self.step_period);
+
+            if !self.run_loop(start_time.elapsed()) {
+                return Ok(());
+            }
+        }
+    }
+}
 
-            if !self.paused {
-                self.early_processes
-                    .iter_mut()
-                    .for_each(|process| process.step(&self.components, &self.input));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                self.normal_processes
-                    .iter_mut()
-                    .for_each(|process| process.step(&self.components, &self.input));
+    #[test]
+    fn resource_access_conflicts() {
+        let none = CompFlags::empty();
+        let watcher = ComponentAccess::new(none, none).writes_resource::<Input>();
+        let player = ComponentAccess::new(none, CompFlags::TRANSFORMS)
+            .reads_resource::<GameTime>()
+            .reads_resource::<Input>();
+        let physics = ComponentAccess::new(CompFlags::COLLISION_SHAPES, CompFlags::PHYSICS_BODIES)
+            .reads_resource::<GameTime>();
+        let ui = ComponentAccess::new(CompFlags::all(), CompFlags::UIS).writes_all_resources();
+        let idle = ComponentAccess::new(none, none);
 
-                self.physics_processes
-                    .iter_mut()
-                    .for_each(|process| process.step(&self.components, &self.input));
+        assert!(watcher.conflicts(&player));
+        assert!(player.conflicts(&watcher));
+        // Reading the same resource is fine
+        assert!(!player.conflicts(&physics));
+        assert!(!watcher.conflicts(&physics));
+        assert!(ui.conflicts(&watcher));
+        assert!(!ui.conflicts(&idle));
+        assert!(ComponentAccess::ALL.conflicts(&watcher));
+    }
 
-                self.late_processes
-                    .iter_mut()
-                    .for_each(|process| process.step(&self.components, &self.input));
-            }
+    #[test]
+    fn parent_cycle_detaches_only_its_members() {
+        let components = Components::default();
+        let [root, child, a, b, below] = [(); 5].map(|_| Entity::new());
+        let parents = [
+            (root, None),
+            (child, Some(root)),
+            (a, Some(b)),
+            (b, Some(a)),
+            (below, Some(a)),
+        ];
+        for (e, parent) in parents {
+            components
+                .transforms
+                .write()
+                .unwrap()
+                .add_component(e, Transform::new(parent, pga::Motor::IDENTITY));
+        }
+
+        let mut process = TransformProcess::default();
+        process.step(&components, &Resources::new(), &mut Commands::new());
+        let global_transforms = components.global_transforms.read().unwrap();
+        for e in [root, child] {
+            assert!(global_transforms.has_component(e));
+        }
+        for e in [a, b, below] {
+            assert!(!global_transforms.has_component(e));
         }
+        // Only the cycle is reported, not what hangs off it
+        let mut reported: Vec<_> = process.reported.iter().copied().collect();
+        reported.sort();
+        assert_eq!(reported, vec![a, b]);
     }
 }
@@ -0,0 +1,123 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::{
+        mpsc::{Receiver, SendError, Sender, TryRecvError},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use gilrs::{EventType, Gilrs};
+
+use crate::{framework::Components, game::GameMessage, input::Input, System, SystemMessage};
+
+/// How long to wait for a gamepad event before checking for messages
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Debug)]
+pub enum GamepadError {
+    Gilrs(gilrs::Error),
+    Send(SendError<GameMessage>),
+}
+impl Display for GamepadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gilrs(err) => writeln!(f, "Failed to initialize gilrs: {err}"),
+            Self::Send(err) => writeln!(f, "Failed to send gamepad input to game: {err}"),
+        }
+    }
+}
+impl Error for GamepadError {}
+impl From<gilrs::Error> for GamepadError {
+    fn from(value: gilrs::Error) -> Self {
+        Self::Gilrs(value)
+    }
+}
+impl From<SendError<GameMessage>> for GamepadError {
+    fn from(value: SendError<GameMessage>) -> Self {
+        Self::Send(value)
+    }
+}
+
+#[derive(Debug)]
+pub enum GamepadMessage {
+    Stop,
+}
+impl SystemMessage for GamepadMessage {
+    fn stop_msg() -> Self {
+        Self::Stop
+    }
+    fn system_name() -> &'static str {
+        "Gamepad"
+    }
+}
+
+#[derive(Debug)]
+pub struct GamepadInit {
+    pub game_sender: Sender<GameMessage>,
+}
+
+/// Reads gamepad events with gilrs and passes them through [`Input`], sending any resulting actions
+/// to the game.
+#[derive(Debug)]
+pub struct Gamepads {
+    gilrs: Gilrs,
+    input: Arc<RwLock<Input>>,
+    game_sender: Sender<GameMessage>,
+    receiver: Receiver<GamepadMessage>,
+}
+impl System for Gamepads {
+    type Init = GamepadInit;
+    type InitErr = GamepadError;
+    type Err = GamepadError;
+    type Msg = GamepadMessage;
+
+    fn new(
+        _: &Arc<Components>,
+        input: &Arc<RwLock<Input>>,
+        GamepadInit { game_sender }: GamepadInit,
+        receiver: Receiver<GamepadMessage>,
+    ) -> Result<Self, GamepadError> {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(gilrs::Error::NotImplemented(dummy)) => {
+                log::warn!("Gamepads aren't supported on this platform");
+                dummy
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            gilrs,
+            input: input.clone(),
+            game_sender,
+            receiver,
+        })
+    }
+    fn run(&mut self) -> Result<(), GamepadError> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(GamepadMessage::Stop) | Err(TryRecvError::Disconnected) => return Ok(()),
+                Err(TryRecvError::Empty) => (),
+            }
+            let Some(event) = self.gilrs.next_event_blocking(Some(POLL_TIMEOUT)) else {
+                continue;
+            };
+            match event.event {
+                EventType::Connected => {
+                    log::info!("Gamepad connected: {}", self.gilrs.gamepad(event.id).name())
+                }
+                EventType::Disconnected => log::info!("Gamepad disconnected"),
+                _ => (),
+            }
+            let action_maybe = self
+                .input
+                .write()
+                .unwrap()
+                .handle_gamepad_event(event.event);
+            if let Some(action) = action_maybe {
+                self.game_sender.send(GameMessage::Input(action))?;
+            }
+        }
+    }
+}
@@ -4,6 +4,8 @@ use hydrolox_pga3d::prelude as pga;
 use serde::{Deserialize, Serialize};
 use vulkano::buffer::BufferContents;
 
+use crate::reflect::impl_reflect;
+
 #[derive(
     Debug, Default, Clone, Copy, PartialEq, PartialOrd, BufferContents, Serialize, Deserialize,
 )]
@@ -13,6 +15,11 @@ pub struct Vec3 {
     pub y: f32,
     pub z: f32,
 }
+impl_reflect!(Vec3 {
+    x: f32,
+    y: f32,
+    z: f32,
+});
 impl Vec3 {
     pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
     pub const MIN: Self = Self::new(f32::MIN, f32::MIN, f32::MIN);
@@ -0,0 +1,154 @@
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use crate::{
+    framework::Components,
+    game::{Game, GameError, GameInit, GameMessage},
+    input::{Input, InputEvent},
+    profile::Profiler,
+    recording::RecordingErr,
+    render::RenderMessage,
+    System,
+};
+
+/// Runs the game without a window or renderer, as fast as it can step. Real time is simulated,
+/// advancing by exactly one step period per game loop, so replaying a recording always gives
+/// the same result.
+#[derive(Debug)]
+pub struct Headless {
+    game: Game,
+    game_sender: Sender<GameMessage>,
+    /// Drained every loop, since nothing renders
+    render_receiver: Receiver<RenderMessage>,
+    components: Arc<Components>,
+    input: Arc<RwLock<Input>>,
+    step_period: f32,
+    loops: u32,
+}
+impl Headless {
+    /// Starts a game with its processes run one at a time. `input` only receives events from
+    /// [`Headless::handle_event`] and recordings being replayed.
+    pub fn new(input: Input, step_period: f32) -> Result<Self, GameError> {
+        let components = Arc::new(Components::default());
+        let input = Arc::new(RwLock::new(input));
+        let (render_sender, render_receiver) = channel();
+        let (game_sender, game_receiver) = channel();
+        let game = Game::new(
+            &components,
+            &input,
+            GameInit {
+                render_sender,
+                serial_processes: true,
+                step_period,
+                profiler: Arc::new(RwLock::new(Profiler::new())),
+                app_proxy: None,
+            },
+            game_receiver,
+        )?;
+        Ok(Self {
+            game,
+            game_sender,
+            render_receiver,
+            components,
+            input,
+            step_period,
+            loops: 0,
+        })
+    }
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+    pub fn input(&self) -> &RwLock<Input> {
+        &self.input
+    }
+    /// Handles an input event the way the app does with live input, sending any action to the
+    /// game for the next loop.
+    pub fn handle_event(&mut self, event: InputEvent) {
+        if let Some(action) = self.input.write().unwrap().handle_event(event) {
+            let _ = self.game_sender.send(GameMessage::Input(action));
+        }
+    }
+    /// Runs `loops` iterations of the game loop back to back.
+    pub fn run_loops(&mut self, loops: u32) {
+        for _ in 0..loops {
+            self.loops += 1;
+            let real_elapsed = Duration::from_secs_f32(self.step_period * self.loops as f32);
+            self.game.run_loop(real_elapsed);
+            self.render_receiver.try_iter().for_each(drop);
+        }
+    }
+    /// Replays the recording at `path` from the next loop until it finishes. Returns the number of
+    /// loops it took.
+    pub fn replay<P: AsRef<Path>>(&mut self, path: P) -> Result<u32, RecordingErr> {
+        self.input.write().unwrap().start_playback(path)?;
+        let start = self.loops;
+        while self.input.read().unwrap().is_playing_back() {
+            self.run_loops(1);
+        }
+        Ok(self.loops - start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{
+        event::ElementState,
+        keyboard::{Key, KeyCode, PhysicalKey},
+    };
+
+    use super::*;
+
+    const STEP_PERIOD: f32 = 1.0 / 60.0;
+
+    fn key(code: KeyCode, text: &str, state: ElementState) -> InputEvent {
+        InputEvent::Key {
+            logical: Key::Character(text.into()),
+            physical: PhysicalKey::Code(code),
+            state,
+            repeat: false,
+            text: (state == ElementState::Pressed).then(|| text.into()),
+        }
+    }
+
+    /// Every transform's motor, in the order their entities were created.
+    fn motors(headless: &Headless) -> Vec<String> {
+        let transforms = headless.components().transforms.read().unwrap();
+        let mut entities: Vec<_> = transforms.iter().map(|(e, _)| e).collect();
+        entities.sort();
+        // Entity IDs differ between games, so motors are compared in creation order
+        entities
+            .into_iter()
+            .map(|e| format!("{:?}", transforms.get(e).unwrap().motor))
+            .collect()
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_transforms() {
+        let path = std::env::temp_dir().join(format!("replay_test_{}.bin", std::process::id()));
+
+        let mut recorded = Headless::new(Input::new().unwrap(), STEP_PERIOD).unwrap();
+        recorded.input().write().unwrap().start_recording(&path);
+        recorded.run_loops(5);
+        recorded.handle_event(key(KeyCode::KeyW, "w", ElementState::Pressed));
+        recorded.run_loops(10);
+        recorded.handle_event(key(KeyCode::KeyD, "d", ElementState::Pressed));
+        recorded.handle_event(InputEvent::MouseDelta(12.0, -4.0));
+        recorded.run_loops(7);
+        recorded.handle_event(key(KeyCode::KeyW, "w", ElementState::Released));
+        recorded.handle_event(key(KeyCode::KeyD, "d", ElementState::Released));
+        recorded.run_loops(1);
+        recorded.input().write().unwrap().stop_recording().unwrap();
+
+        let mut replayed = Headless::new(Input::new().unwrap(), STEP_PERIOD).unwrap();
+        let loops = replayed.replay(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loops.unwrap(), 23);
+        assert_eq!(motors(&replayed), motors(&recorded));
+    }
+}
@@ -1,70 +1,230 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, path::Path, sync::RwLock, time::SystemTime};
 
-use ahash::AHashMap;
-use bitflags::bitflags;
+use ahash::{AHashMap, AHashSet};
 use egui::Pos2;
 use serde::{Deserialize, Serialize};
 use serde_yml as yml;
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, KeyEvent, MouseButton},
+    event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta},
     keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey, SmolStr},
 };
 
-bitflags! {
+use crate::{
+    action::{Action, ActionDef, ActionId, ActionKind, ActionManifest, ActionValue},
+    buttons::ButtonTransitions,
+    context::ContextStack,
+    cursor::CursorMode,
+    recording::{Playback, Recording, RecordingErr},
+};
+
+const BINDS_PATH: &str = "binds.yaml";
 
-    #[repr(transparent)]
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-    pub struct ActionFlags: u64 {
-        const PRIMARY_INTERACT = 0;
-        const SECONDARY_INTERACT = 1;
-        const PAUSE = 2;
-        const LOOK = 4;
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BindType {
+    Key(KeyCode),
+    KeyMod(KeyCode, ModifiersState),
+    Named(NamedKey),
+    Character(SmolStr),
+    MouseButton(MouseButton),
+    GamepadButton(gilrs::Button),
+}
+impl Display for BindType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(kc) => write!(f, "{kc:?}"),
+            Self::KeyMod(kc, mods) => {
+                for (flag, name) in [
+                    (ModifiersState::CONTROL, "Ctrl"),
+                    (ModifiersState::SHIFT, "Shift"),
+                    (ModifiersState::ALT, "Alt"),
+                    (ModifiersState::SUPER, "Super"),
+                ] {
+                    if mods.contains(flag) {
+                        write!(f, "{name}+")?;
+                    }
+                }
+                write!(f, "{kc:?}")
+            }
+            Self::Named(named) => write!(f, "{named:?}"),
+            Self::Character(ch) => write!(f, "'{ch}'"),
+            Self::MouseButton(button) => write!(f, "Mouse {button:?}"),
+            Self::GamepadButton(button) => write!(f, "Gamepad {button:?}"),
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[repr(u64)]
-pub enum Action {
-    PrimaryInteract = ActionFlags::PRIMARY_INTERACT.bits(),
-    SecondaryInteract = ActionFlags::SECONDARY_INTERACT.bits(),
-    Pause = ActionFlags::PAUSE.bits(),
-    Look(f32, f32) = ActionFlags::LOOK.bits(),
+/// Where an axis action gets its value from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AxisSource {
+    /// Relative mouse motion in pixels, sent as an action for every motion event
+    MouseMove,
+    /// Two buttons combined into a 1D axis, -1 while only `negative` is held and 1 while only
+    /// `positive` is held
+    Buttons1d {
+        negative: BindType,
+        positive: BindType,
+    },
+    /// Four buttons combined into a 2D vector with a length of at most 1
+    Buttons2d {
+        up: BindType,
+        down: BindType,
+        left: BindType,
+        right: BindType,
+    },
+    /// A single gamepad axis, from -1 to 1
+    GamepadAxis(gilrs::Axis),
+    /// How far an analog gamepad button like a trigger is pressed, from 0 to 1
+    GamepadTrigger(gilrs::Button),
+    /// Two gamepad axes combined into a 2D vector, such as a stick
+    GamepadStick { x: gilrs::Axis, y: gilrs::Axis },
 }
-impl Action {
-    fn from_bind_out(bind_out: BindOut) -> Option<Self> {
-        match bind_out {
-            BindOut::PrimaryInteract => Some(Self::PrimaryInteract),
-            BindOut::SecondaryInteract => Some(Self::SecondaryInteract),
-            BindOut::Pause => Some(Self::Pause),
-            _ => None,
+impl AxisSource {
+    fn kind(&self) -> ActionKind {
+        match self {
+            Self::Buttons1d { .. } | Self::GamepadAxis(_) | Self::GamepadTrigger(_) => {
+                ActionKind::Axis1d
+            }
+            Self::MouseMove | Self::Buttons2d { .. } | Self::GamepadStick { .. } => {
+                ActionKind::Axis2d
+            }
+        }
+    }
+    /// Names of the buttons making up a composite axis, in the same order as [`Self::buttons`]
+    pub fn button_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Buttons1d { .. } => &["negative", "positive"],
+            Self::Buttons2d { .. } => &["up", "down", "left", "right"],
+            _ => &[],
+        }
+    }
+    fn buttons_mut(&mut self) -> Vec<&mut BindType> {
+        match self {
+            Self::MouseMove
+            | Self::GamepadAxis(_)
+            | Self::GamepadTrigger(_)
+            | Self::GamepadStick { .. } => Vec::new(),
+            Self::Buttons1d { negative, positive } => vec![negative, positive],
+            Self::Buttons2d {
+                up,
+                down,
+                left,
+                right,
+            } => vec![up, down, left, right],
         }
     }
-    pub fn discriminant(&self) -> ActionFlags {
-        //Safe according to https://doc.rust-lang.org/std/mem/fn.discriminant.html#accessing-the-numeric-value-of-the-discriminant
-        unsafe { *<*const _>::from(self).cast::<ActionFlags>() }
+    /// Whether values are within -1..=1, unlike mouse motion which is measured in pixels
+    pub fn is_normalized(&self) -> bool {
+        !matches!(self, Self::MouseMove)
+    }
+    /// The buttons making up a composite axis, in the order their held state is stored
+    pub fn buttons(&self) -> Vec<&BindType> {
+        match self {
+            Self::MouseMove
+            | Self::GamepadAxis(_)
+            | Self::GamepadTrigger(_)
+            | Self::GamepadStick { .. } => Vec::new(),
+            Self::Buttons1d { negative, positive } => vec![negative, positive],
+            Self::Buttons2d {
+                up,
+                down,
+                left,
+                right,
+            } => vec![up, down, left, right],
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-enum BindOut {
-    PrimaryInteract,
-    SecondaryInteract,
-    Pause,
-    MoveForward,
-    MoveLeft,
-    MoveRight,
-    MoveBack,
+impl Display for AxisSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MouseMove => write!(f, "Mouse motion"),
+            Self::GamepadAxis(axis) => write!(f, "Gamepad {axis:?}"),
+            Self::GamepadTrigger(button) => write!(f, "Gamepad {button:?}"),
+            Self::GamepadStick { x, y } => write!(f, "Gamepad {x:?}/{y:?}"),
+            Self::Buttons1d { .. } | Self::Buttons2d { .. } => {
+                let buttons: Vec<String> = self.buttons().iter().map(|b| b.to_string()).collect();
+                write!(f, "{}", buttons.join("/"))
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-enum BindType {
-    Key(KeyCode),
-    KeyMod(KeyCode, ModifiersState),
-    Named(NamedKey),
-    Character(SmolStr),
-    MouseButton(MouseButton),
-    MouseMove,
+/// Shapes an axis value by its magnitude, after the dead zone is removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Raises the magnitude to the given power, values above 1 give finer control of small inputs
+    Power(f32),
+}
+impl ResponseCurve {
+    fn apply(&self, magnitude: f32) -> f32 {
+        match self {
+            Self::Linear => magnitude,
+            Self::Power(exp) => magnitude.powf(*exp),
+        }
+    }
+}
+
+fn default_sensitivity() -> f32 {
+    1.0
+}
+
+/// Binds an [`AxisSource`] to an axis action, along with how its raw value is processed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub action: String,
+    pub source: AxisSource,
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f32,
+    #[serde(default)]
+    pub invert_x: bool,
+    /// Ignored for 1D axes
+    #[serde(default)]
+    pub invert_y: bool,
+    /// Magnitudes up to this are treated as 0, and larger ones are rescaled so the dead zone's
+    /// edge starts from 0. Ignored for mouse motion
+    #[serde(default)]
+    pub dead_zone: f32,
+    /// Ignored for mouse motion
+    #[serde(default)]
+    pub curve: ResponseCurve,
+}
+impl AxisBinding {
+    fn new(action: &str, source: AxisSource) -> Self {
+        Self {
+            action: action.into(),
+            source,
+            sensitivity: default_sensitivity(),
+            invert_x: false,
+            invert_y: false,
+            dead_zone: 0.0,
+            curve: ResponseCurve::default(),
+        }
+    }
+    /// Applies inversion, the dead zone, response curve and sensitivity to a raw value. Mouse
+    /// motion is only inverted and scaled, since the dead zone and curve expect values in -1..=1.
+    fn process(&self, (mut x, mut y): (f32, f32)) -> (f32, f32) {
+        if self.invert_x {
+            x = -x;
+        }
+        if self.invert_y {
+            y = -y;
+        }
+        if !self.source.is_normalized() {
+            return (x * self.sensitivity, y * self.sensitivity);
+        }
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude <= self.dead_zone {
+            return (0.0, 0.0);
+        }
+        let shaped = self
+            .curve
+            .apply((magnitude - self.dead_zone) / (1.0 - self.dead_zone).max(f32::EPSILON));
+        let scale = shaped * self.sensitivity / magnitude;
+        (x * scale, y * scale)
+    }
 }
 
 #[derive(Debug)]
@@ -92,11 +252,80 @@ impl From<std::io::Error> for BindsErr {
     }
 }
 
+/// Bindings that get in each other's way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// An input bound more than once, along with the action of each binding in order. Only its
+    /// first button binding triggers, while its axis bindings all take effect.
+    Duplicate {
+        bind: BindType,
+        actions: Vec<String>,
+    },
+    /// A key bound with modifiers to a different button action than the key alone. While the
+    /// modifiers are held, pressing the key triggers only `bind`'s action.
+    Shadowed {
+        bind: BindType,
+        action: String,
+        shadowed: BindType,
+        shadowed_action: String,
+    },
+}
+impl Conflict {
+    /// The input that conflicts
+    pub fn bind(&self) -> &BindType {
+        match self {
+            Self::Duplicate { bind, .. } | Self::Shadowed { bind, .. } => bind,
+        }
+    }
+}
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duplicate { bind, actions } => {
+                write!(f, "{bind} is bound to {}", actions.join(", "))
+            }
+            Self::Shadowed {
+                bind,
+                action,
+                shadowed,
+                shadowed_action,
+            } => write!(
+                f,
+                "{bind} ({action}) overrides {shadowed} ({shadowed_action}) while held"
+            ),
+        }
+    }
+}
+
+/// What the next input pressed will be bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebindTarget {
+    /// Adds a binding for the button action with this name
+    Button(String),
+    /// Replaces one of the buttons of a composite axis binding, indexed by its position in the
+    /// bindings and in [`AxisSource::buttons`]
+    AxisButton { axis: usize, slot: usize },
+}
+
+/// Binds an input to a button action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ButtonBinding {
+    action: String,
+    bind: BindType,
+}
+
+/// Maps inputs to the names of the actions in the [`ActionManifest`] they trigger.
 #[derive(Debug, Serialize, Deserialize)]
-struct Bindings(AHashMap<BindType, BindOut>);
+struct Bindings {
+    /// An input may be bound to several actions, but only the first of them triggers
+    #[serde(default)]
+    buttons: Vec<ButtonBinding>,
+    #[serde(default)]
+    axes: Vec<AxisBinding>,
+}
 impl Bindings {
     fn new() -> Result<Self, BindsErr> {
-        if let Ok(binds_file) = std::fs::File::open("binds.yaml") {
+        if let Ok(binds_file) = std::fs::File::open(BINDS_PATH) {
             return Ok(yml::from_reader(binds_file)?);
         } else {
             log::info!("Bindings file not found, initializing with default binds");
@@ -106,28 +335,245 @@ impl Bindings {
         Ok(me)
     }
     fn default_binds() -> Self {
-        Self(AHashMap::from([
-            (BindType::Key(KeyCode::KeyW), BindOut::MoveForward),
-            (BindType::Key(KeyCode::KeyA), BindOut::MoveLeft),
-            (BindType::Key(KeyCode::KeyD), BindOut::MoveRight),
-            (BindType::Key(KeyCode::KeyS), BindOut::MoveBack),
+        let buttons = [
+            (BindType::MouseButton(MouseButton::Left), "PrimaryInteract"),
             (
-                BindType::MouseButton(MouseButton::Left),
-                BindOut::PrimaryInteract,
+                BindType::MouseButton(MouseButton::Right),
+                "SecondaryInteract",
             ),
             (
-                BindType::MouseButton(MouseButton::Right),
-                BindOut::SecondaryInteract,
+                BindType::GamepadButton(gilrs::Button::RightTrigger2),
+                "PrimaryInteract",
+            ),
+            (
+                BindType::GamepadButton(gilrs::Button::LeftTrigger2),
+                "SecondaryInteract",
+            ),
+            (BindType::GamepadButton(gilrs::Button::Start), "Pause"),
+            (BindType::Key(KeyCode::F3), "ToggleInspector"),
+            (BindType::Key(KeyCode::F4), "ToggleProfiler"),
+            (BindType::Key(KeyCode::Backquote), "ToggleConsole"),
+            (BindType::Key(KeyCode::F2), "ToggleSettings"),
+            (BindType::Key(KeyCode::AltLeft), "ToggleCursor"),
+            (BindType::Key(KeyCode::Escape), "Pause"),
+            (BindType::Key(KeyCode::F10), "Step"),
+        ]
+        .into_iter()
+        .map(|(bind, action)| ButtonBinding {
+            action: action.into(),
+            bind,
+        })
+        .collect();
+        let axes = vec![
+            AxisBinding::new(
+                "Move",
+                AxisSource::Buttons2d {
+                    up: BindType::Key(KeyCode::KeyW),
+                    down: BindType::Key(KeyCode::KeyS),
+                    left: BindType::Key(KeyCode::KeyA),
+                    right: BindType::Key(KeyCode::KeyD),
+                },
             ),
-        ]))
+            AxisBinding::new(
+                "Move",
+                AxisSource::GamepadStick {
+                    x: gilrs::Axis::LeftStickX,
+                    y: gilrs::Axis::LeftStickY,
+                },
+            ),
+            AxisBinding::new("Look", AxisSource::MouseMove),
+            AxisBinding {
+                sensitivity: 6.0,
+                invert_y: true,
+                ..AxisBinding::new(
+                    "Look",
+                    AxisSource::GamepadStick {
+                        x: gilrs::Axis::RightStickX,
+                        y: gilrs::Axis::RightStickY,
+                    },
+                )
+            },
+        ];
+        Self { buttons, axes }
+    }
+    /// Looks up the action each input is bound to, skipping bindings that don't fit the action.
+    /// Inputs bound to several button actions keep the first.
+    fn resolve(&self, manifest: &ActionManifest) -> ResolvedBindings {
+        let mut resolved = ResolvedBindings::default();
+        for ButtonBinding { action: name, bind } in &self.buttons {
+            let Some(id) = manifest.id(name) else {
+                log::warn!("{bind:?} is bound to unknown action \"{name}\"");
+                continue;
+            };
+            if manifest.get(id).kind != ActionKind::Button {
+                log::warn!("{bind:?} can't be bound to action \"{name}\", it isn't a Button");
+                continue;
+            }
+            resolved.buttons.entry(bind.clone()).or_insert(id);
+        }
+        for binding in &self.axes {
+            let name = &binding.action;
+            let Some(id) = manifest.id(name) else {
+                log::warn!("{:?} is bound to unknown action \"{name}\"", binding.source);
+                continue;
+            };
+            let expected = binding.source.kind();
+            if manifest.get(id).kind != expected {
+                log::warn!(
+                    "{:?} can't be bound to action \"{name}\", it must be a {expected:?}",
+                    binding.source
+                );
+                continue;
+            }
+            let axis = resolved.axes.len();
+            let buttons = binding.source.buttons();
+            for (slot, bind) in buttons.iter().enumerate() {
+                resolved
+                    .axis_buttons
+                    .entry((*bind).clone())
+                    .or_default()
+                    .push((axis, slot));
+            }
+            resolved.axes.push(ResolvedAxis {
+                id,
+                binding: binding.clone(),
+                held: vec![false; buttons.len()],
+            });
+        }
+        resolved
+    }
+    /// Inputs bound more than once, then keys whose binding with modifiers overrides their
+    /// binding alone. Each sorted by input.
+    fn conflicts(&self) -> Vec<Conflict> {
+        let mut uses: Vec<(&BindType, &str)> = self
+            .buttons
+            .iter()
+            .map(|binding| (&binding.bind, binding.action.as_str()))
+            .collect();
+        for binding in &self.axes {
+            for bind in binding.source.buttons() {
+                uses.push((bind, &binding.action));
+            }
+        }
+        let mut by_bind = AHashMap::<&BindType, Vec<String>>::new();
+        for (bind, name) in uses {
+            by_bind.entry(bind).or_default().push(name.into());
+        }
+        let mut duplicates: Vec<Conflict> = by_bind
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(bind, actions)| Conflict::Duplicate {
+                bind: bind.clone(),
+                actions,
+            })
+            .collect();
+        duplicates.sort_by_key(|conflict| conflict.bind().to_string());
+
+        // Only the first binding of each input triggers, so only those can shadow each other
+        let mut first = AHashMap::<&BindType, &str>::new();
+        for binding in &self.buttons {
+            first.entry(&binding.bind).or_insert(&binding.action);
+        }
+        let mut shadowed: Vec<Conflict> = first
+            .iter()
+            .filter_map(|(&bind, &action)| {
+                let BindType::KeyMod(kc, _) = bind else {
+                    return None;
+                };
+                let key = BindType::Key(*kc);
+                let &shadowed_action = first.get(&key)?;
+                (shadowed_action != action).then(|| Conflict::Shadowed {
+                    bind: bind.clone(),
+                    action: action.into(),
+                    shadowed: key,
+                    shadowed_action: shadowed_action.into(),
+                })
+            })
+            .collect();
+        shadowed.sort_by_key(|conflict| conflict.bind().to_string());
+
+        duplicates.extend(shadowed);
+        duplicates
     }
     fn save(&self) -> Result<(), BindsErr> {
-        let file = std::fs::File::create("binds.yaml")?;
+        let file = std::fs::File::create(BINDS_PATH)?;
         yml::to_writer(&file, self)?;
         Ok(())
     }
 }
 
+/// When `binds.yaml` was last modified, or None if it doesn't exist
+fn binds_modified() -> Option<SystemTime> {
+    std::fs::metadata(BINDS_PATH).ok()?.modified().ok()
+}
+
+#[derive(Debug)]
+struct ResolvedAxis {
+    id: ActionId,
+    binding: AxisBinding,
+    /// Held state of each of the source's buttons, in the order of [`AxisSource::buttons`]
+    held: Vec<bool>,
+}
+impl ResolvedAxis {
+    /// The processed value of an absolute axis. Always 0 for relative sources.
+    fn value(&self, gamepad: &GamepadState) -> (f32, f32) {
+        let dir = |negative: bool, positive: bool| positive as i8 as f32 - negative as i8 as f32;
+        let raw = match self.binding.source {
+            AxisSource::MouseMove => return (0.0, 0.0),
+            AxisSource::GamepadAxis(axis) => (gamepad.axis(axis), 0.0),
+            AxisSource::GamepadTrigger(button) => (gamepad.button(button), 0.0),
+            AxisSource::GamepadStick { x, y } => (gamepad.axis(x), gamepad.axis(y)),
+            AxisSource::Buttons1d { .. } | AxisSource::Buttons2d { .. } => match self.held[..] {
+                [negative, positive] => (dir(negative, positive), 0.0),
+                [up, down, left, right] => {
+                    let (x, y) = (dir(left, right), dir(down, up));
+                    let magnitude = (x * x + y * y).sqrt();
+                    if magnitude > 1.0 {
+                        (x / magnitude, y / magnitude)
+                    } else {
+                        (x, y)
+                    }
+                }
+                _ => return (0.0, 0.0),
+            },
+        };
+        self.binding.process(raw)
+    }
+}
+
+/// Latest analog values reported by connected gamepads. Values from every gamepad are merged, so
+/// any of them can drive an action.
+#[derive(Debug, Default)]
+struct GamepadState {
+    axes: AHashMap<gilrs::Axis, f32>,
+    buttons: AHashMap<gilrs::Button, f32>,
+}
+impl GamepadState {
+    fn axis(&self, axis: gilrs::Axis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+    fn button(&self, button: gilrs::Button) -> f32 {
+        self.buttons.get(&button).copied().unwrap_or(0.0)
+    }
+}
+
+/// [`Bindings`] with action names looked up, ready for handling input.
+#[derive(Debug, Default)]
+struct ResolvedBindings {
+    buttons: AHashMap<BindType, ActionId>,
+    axes: Vec<ResolvedAxis>,
+    /// The axes each button is part of, and its slot in [`ResolvedAxis::held`]
+    axis_buttons: AHashMap<BindType, Vec<(usize, usize)>>,
+}
+impl ResolvedBindings {
+    /// Updates the held state of every composite axis `bind` is part of.
+    fn set_axis_button(&mut self, bind: &BindType, pressed: bool) {
+        for &(axis, slot) in self.axis_buttons.get(bind).into_iter().flatten() {
+            self.axes[axis].held[slot] = pressed;
+        }
+    }
+}
+
 //taken from egui_winit
 fn key_from_key_code(key: winit::keyboard::KeyCode) -> Option<egui::Key> {
     use egui::Key;
@@ -318,6 +764,15 @@ fn key_from_named_key(named_key: winit::keyboard::NamedKey) -> Option<egui::Key>
     })
 }
 
+/// Returns false for control characters and the private use area, which macOS uses for function
+/// keys, so keys like backspace and arrows don't type text.
+fn is_printable_char(ch: char) -> bool {
+    let private_use = ('\u{e000}'..='\u{f8ff}').contains(&ch)
+        || ('\u{f0000}'..='\u{ffffd}').contains(&ch)
+        || ('\u{100000}'..='\u{10fffd}').contains(&ch);
+    !private_use && !ch.is_control()
+}
+
 fn mouse_button_to_pointer_button(mb: MouseButton) -> Option<egui::PointerButton> {
     match mb {
         MouseButton::Left => Some(egui::PointerButton::Primary),
@@ -329,39 +784,314 @@ fn mouse_button_to_pointer_button(mb: MouseButton) -> Option<egui::PointerButton
     }
 }
 
+/// An input event reaching [`Input`], as passed to [`Input::handle_event`]. Everything `Input`
+/// does depends only on the order of these, so they can be recorded and replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        logical: Key,
+        physical: PhysicalKey,
+        state: ElementState,
+        repeat: bool,
+        /// Text the key typed, if it was pressed
+        text: Option<SmolStr>,
+    },
+    MouseButton {
+        state: ElementState,
+        button: MouseButton,
+    },
+    MouseDelta(f32, f32),
+    CursorMoved(f64, f64),
+    MouseWheel(MouseScrollDelta),
+    Modifiers(ModifiersState),
+    Ime(Ime),
+    Focused(bool),
+    Gamepad {
+        event: gilrs::EventType,
+    },
+}
+
 #[derive(Debug)]
 pub struct Input {
+    manifest: ActionManifest,
     bindings: Bindings,
+    resolved_bindings: ResolvedBindings,
     current_modifiers: ModifiersState,
-    move_inputs: [bool; 4],
+    /// The inputs holding down each button action, indexed by [`ActionId`]
+    held: Vec<AHashSet<BindType>>,
+    /// Presses and releases of each button action since they were last taken, indexed by
+    /// [`ActionId`]
+    transitions: Vec<ButtonTransitions>,
+    gamepad: GamepadState,
+    contexts: ContextStack,
+    /// Set while waiting for an input to bind
+    rebinding: Option<RebindTarget>,
+    /// Modification time of `binds.yaml` when it was last loaded or saved
+    binds_modified: Option<SystemTime>,
     egui_events: Vec<egui::Event>,
     last_cusror_pos: (f64, f64),
+    cursor_mode: CursorMode,
+    /// Whether the window has keyboard focus
+    focused: bool,
+    /// Game loop steps run so far, including ones while paused
+    step: u64,
+    recording: Option<Recording>,
+    /// While set, events from the recording replace live input
+    playback: Option<Playback>,
 }
 impl Input {
     pub fn new() -> Result<Self, BindsErr> {
-        Ok(Self {
-            bindings: Bindings::new()?,
+        let mut me = Self::with_bindings(ActionManifest::load()?, Bindings::new()?);
+        me.binds_modified = binds_modified();
+        Ok(me)
+    }
+    fn with_bindings(manifest: ActionManifest, bindings: Bindings) -> Self {
+        Self {
+            resolved_bindings: bindings.resolve(&manifest),
+            held: vec![AHashSet::new(); manifest.len()],
+            transitions: vec![ButtonTransitions::default(); manifest.len()],
+            gamepad: GamepadState::default(),
+            contexts: ContextStack::new(&manifest),
+            rebinding: None,
+            binds_modified: None,
+            manifest,
+            bindings,
             current_modifiers: ModifiersState::default(),
-            move_inputs: [false; 4],
             egui_events: Vec::default(),
             last_cusror_pos: (0.0, 0.0),
-        })
+            cursor_mode: CursorMode::default(),
+            focused: true,
+            step: 0,
+            recording: None,
+            playback: None,
+        }
+    }
+    /// Replaces the current bindings with the ones in `binds.yaml`.
+    pub fn reload_bindings(&mut self) -> Result<(), BindsErr> {
+        self.bindings = Bindings::new()?;
+        self.binds_modified = binds_modified();
+        self.apply_bindings();
+        Ok(())
     }
-    fn handle_bind_out(&mut self, bind_out: BindOut, state: ElementState) -> Option<Action> {
-        if let Some(action) = Action::from_bind_out(bind_out) {
-            if state.is_pressed() {
-                return Some(action);
+    /// Reloads `binds.yaml` if it changed since it was last loaded or saved. The file is parsed
+    /// before the write lock is taken, and the current bindings are kept if it fails to parse.
+    pub fn reload_changed_bindings(input: &RwLock<Input>) {
+        let Some(modified) = binds_modified() else {
+            return;
+        };
+        if input.read().unwrap().binds_modified == Some(modified) {
+            return;
+        }
+        let result = Bindings::new();
+        let mut input = input.write().unwrap();
+        input.binds_modified = Some(modified);
+        match result {
+            Ok(bindings) => {
+                input.bindings = bindings;
+                input.apply_bindings();
+                log::info!("Reloaded {BINDS_PATH}");
             }
-        } else {
-            match bind_out {
-                BindOut::MoveForward => self.move_inputs[0] = state.is_pressed(),
-                BindOut::MoveLeft => self.move_inputs[1] = state.is_pressed(),
-                BindOut::MoveRight => self.move_inputs[2] = state.is_pressed(),
-                BindOut::MoveBack => self.move_inputs[3] = state.is_pressed(),
-                _ => panic!("Shouldn't happen"),
+            Err(err) => log::error!(
+                "Failed to reload {BINDS_PATH}, keeping current bindings: {}",
+                err.to_string().trim_end()
+            ),
+        }
+    }
+    fn apply_bindings(&mut self) {
+        self.resolved_bindings = self.bindings.resolve(&self.manifest);
+        self.held.iter_mut().for_each(|held| held.clear());
+    }
+    pub fn save_bindings(&mut self) -> Result<(), BindsErr> {
+        self.bindings.save()?;
+        self.binds_modified = binds_modified();
+        Ok(())
+    }
+    pub fn restore_default_bindings(&mut self) {
+        self.bindings = Bindings::default_binds();
+        self.apply_bindings();
+    }
+    /// Every button binding, sorted by action name
+    pub fn button_bindings(&self) -> Vec<(&BindType, &str)> {
+        let mut bindings: Vec<_> = self
+            .bindings
+            .buttons
+            .iter()
+            .map(|binding| (&binding.bind, binding.action.as_str()))
+            .collect();
+        bindings.sort_by_key(|(bind, name)| (*name, bind.to_string()));
+        bindings
+    }
+    /// Binds the input to a button action, keeping any other actions it's bound to. Returns
+    /// those actions, which the new binding conflicts with.
+    pub fn bind_button(&mut self, bind: BindType, action: &str) -> Vec<String> {
+        let others: Vec<String> = self
+            .bindings
+            .buttons
+            .iter()
+            .filter(|binding| binding.bind == bind && binding.action != action)
+            .map(|binding| binding.action.clone())
+            .collect();
+        if !self
+            .bindings
+            .buttons
+            .iter()
+            .any(|binding| binding.bind == bind && binding.action == action)
+        {
+            self.bindings.buttons.push(ButtonBinding {
+                action: action.into(),
+                bind,
+            });
+            self.apply_bindings();
+        }
+        others
+    }
+    /// Returns false if the input wasn't bound to the button action.
+    pub fn unbind_button(&mut self, bind: &BindType, action: &str) -> bool {
+        let len = self.bindings.buttons.len();
+        self.bindings
+            .buttons
+            .retain(|binding| !(&binding.bind == bind && binding.action == action));
+        self.apply_bindings();
+        self.bindings.buttons.len() != len
+    }
+    pub fn axis_bindings(&self) -> &[AxisBinding] {
+        &self.bindings.axes
+    }
+    pub fn set_axis_binding(&mut self, index: usize, binding: AxisBinding) {
+        self.bindings.axes[index] = binding;
+        self.apply_bindings();
+    }
+    pub fn remove_axis_binding(&mut self, index: usize) -> AxisBinding {
+        let binding = self.bindings.axes.remove(index);
+        self.apply_bindings();
+        binding
+    }
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.bindings.conflicts()
+    }
+    /// Binds the next key, gamepad button or mouse button other than the left one to `target`,
+    /// and saves the bindings. Pressing the left mouse button cancels.
+    pub fn start_rebind(&mut self, target: RebindTarget) {
+        self.rebinding = Some(target);
+    }
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+    pub fn rebind_target(&self) -> Option<&RebindTarget> {
+        self.rebinding.as_ref()
+    }
+    /// Binds `bind` to the rebind target, if there is one. Returns true if the input was used.
+    fn try_rebind(&mut self, bind: BindType) -> bool {
+        let Some(target) = self.rebinding.take() else {
+            return false;
+        };
+        match target {
+            RebindTarget::Button(action) => {
+                log::info!("Bound {bind} to {action}");
+                let others = self.bind_button(bind.clone(), &action);
+                if !others.is_empty() {
+                    log::warn!("{bind} is also bound to {}", others.join(", "));
+                }
+            }
+            RebindTarget::AxisButton { axis, slot } => {
+                let Some(binding) = self.bindings.axes.get_mut(axis) else {
+                    return true;
+                };
+                if let Some(button) = binding.source.buttons_mut().into_iter().nth(slot) {
+                    log::info!("Bound {bind} to {}", binding.action);
+                    *button = bind;
+                }
             }
         }
-        None
+        self.apply_bindings();
+        if let Err(err) = self.save_bindings() {
+            log::error!("Failed to save bindings: {err}");
+        }
+        true
+    }
+    pub fn action_id(&self, name: &str) -> Option<ActionId> {
+        self.manifest.id(name)
+    }
+    pub fn action_def(&self, id: ActionId) -> &ActionDef {
+        self.manifest.get(id)
+    }
+    pub fn actions(&self) -> impl Iterator<Item = (ActionId, &ActionDef)> {
+        self.manifest.iter()
+    }
+    pub fn contexts(&self) -> &ContextStack {
+        &self.contexts
+    }
+    pub fn contexts_mut(&mut self) -> &mut ContextStack {
+        &mut self.contexts
+    }
+    /// Returns true while an input bound to the button action is held down, and no input context
+    /// blocks it.
+    pub fn is_held(&self, id: ActionId) -> bool {
+        !self.held[id.index()].is_empty() && self.contexts.claimed_by(id).is_some()
+    }
+    /// Current value of a 1D axis action, summed over every absolute binding it has. Relative
+    /// sources like mouse motion are only sent as actions and don't contribute. 0 while an input
+    /// context blocks the action.
+    pub fn axis1d(&self, id: ActionId) -> f32 {
+        self.axis2d(id).0
+    }
+    /// Current value of a 2D axis action, summed over every absolute binding it has. Relative
+    /// sources like mouse motion are only sent as actions and don't contribute. 0 while an input
+    /// context blocks the action.
+    pub fn axis2d(&self, id: ActionId) -> (f32, f32) {
+        if self.contexts.claimed_by(id).is_none() {
+            return (0.0, 0.0);
+        }
+        self.resolved_bindings
+            .axes
+            .iter()
+            .filter(|axis| axis.id == id)
+            .map(|axis| axis.value(&self.gamepad))
+            .fold((0.0, 0.0), |sum, value| (sum.0 + value.0, sum.1 + value.1))
+    }
+    /// Takes the presses and releases of every button action since this was last called, indexed
+    /// by [`ActionId`].
+    pub fn take_button_transitions(&mut self) -> Vec<ButtonTransitions> {
+        let len = self.transitions.len();
+        std::mem::replace(
+            &mut self.transitions,
+            vec![ButtonTransitions::default(); len],
+        )
+    }
+    /// Sends an action when the button action is pressed or released. The action stays held while
+    /// any input bound to it is down, so key repeats and presses and releases of a second input
+    /// are ignored.
+    fn handle_bind_out(
+        &mut self,
+        id: ActionId,
+        bind: BindType,
+        state: ElementState,
+    ) -> Option<Action> {
+        let pressed = state.is_pressed();
+        let held = &mut self.held[id.index()];
+        let was_held = !held.is_empty();
+        let changed = if pressed {
+            held.insert(bind)
+        } else {
+            held.remove(&bind)
+        };
+        if !changed || was_held != held.is_empty() {
+            return None;
+        }
+        let transitions = &mut self.transitions[id.index()];
+        if pressed {
+            transitions.presses = transitions.presses.saturating_add(1);
+        } else {
+            transitions.releases = transitions.releases.saturating_add(1);
+        }
+        Some(Action {
+            id,
+            value: if pressed {
+                ActionValue::Pressed
+            } else {
+                ActionValue::Released
+            },
+        })
     }
     pub fn egui_modifiers(&self) -> egui::Modifiers {
         let super_mod = self.current_modifiers.contains(ModifiersState::SUPER);
@@ -382,68 +1112,266 @@ impl Input {
             },
         }
     }
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
     pub fn egui_events(&mut self) -> Vec<egui::Event> {
         std::mem::take(&mut self.egui_events)
     }
     pub fn handle_key(&mut self, k: KeyEvent) -> Option<Action> {
-        if let Some(key) = match &k.logical_key {
+        self.handle_event(InputEvent::Key {
+            logical: k.logical_key,
+            physical: k.physical_key,
+            state: k.state,
+            repeat: k.repeat,
+            text: k.text,
+        })
+    }
+    pub fn handle_modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.handle_event(InputEvent::Modifiers(modifiers));
+    }
+    pub fn handle_ime(&mut self, ime: Ime) {
+        self.handle_event(InputEvent::Ime(ime));
+    }
+    pub fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        self.handle_event(InputEvent::MouseWheel(delta));
+    }
+    pub fn handle_focused(&mut self, focused: bool) {
+        self.handle_event(InputEvent::Focused(focused));
+    }
+    pub fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        self.handle_event(InputEvent::CursorMoved(position.x, position.y));
+    }
+    pub fn handle_mouse_button(
+        &mut self,
+        state: ElementState,
+        button: MouseButton,
+    ) -> Option<Action> {
+        self.handle_event(InputEvent::MouseButton { state, button })
+    }
+    pub fn handle_mouse_delta(&mut self, delta: (f32, f32)) -> Option<Action> {
+        self.handle_event(InputEvent::MouseDelta(delta.0, delta.1))
+    }
+    /// Handles an event from a gamepad. Events can come from gilrs or be constructed directly to
+    /// simulate a gamepad.
+    pub fn handle_gamepad_event(&mut self, event: gilrs::EventType) -> Option<Action> {
+        self.handle_event(InputEvent::Gamepad { event })
+    }
+    /// Handles a live input event. Ignored during playback, so only the recording drives input.
+    pub fn handle_event(&mut self, event: InputEvent) -> Option<Action> {
+        if self.playback.is_some() {
+            return None;
+        }
+        self.process_event(event)
+    }
+    fn process_event(&mut self, event: InputEvent) -> Option<Action> {
+        if let Some(recording) = &mut self.recording {
+            recording.push(self.step, event.clone());
+        }
+        match event {
+            InputEvent::Key {
+                logical,
+                physical,
+                state,
+                repeat,
+                text,
+            } => self.process_key(logical, physical, state, repeat, text),
+            InputEvent::MouseButton { state, button } => self.process_mouse_button(state, button),
+            InputEvent::MouseDelta(x, y) => self.process_mouse_delta((x, y)),
+            InputEvent::CursorMoved(x, y) => {
+                self.process_cursor_moved(x, y);
+                None
+            }
+            InputEvent::MouseWheel(delta) => {
+                self.process_mouse_wheel(delta);
+                None
+            }
+            InputEvent::Modifiers(modifiers) => {
+                self.current_modifiers = modifiers;
+                None
+            }
+            InputEvent::Ime(ime) => {
+                self.process_ime(ime);
+                None
+            }
+            InputEvent::Focused(focused) => {
+                self.process_focused(focused);
+                None
+            }
+            InputEvent::Gamepad { event } => self.process_gamepad_event(event),
+        }
+    }
+    pub fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+    /// Sets the cursor mode mouse motion is handled for. Doesn't change the window's cursor,
+    /// which the app does when sent [`AppMessage::SetCursorMode`](crate::AppMessage).
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        if mode == CursorMode::Locked && self.cursor_mode != mode {
+            // The hidden cursor is no longer over any UI
+            self.egui_events.push(egui::Event::PointerGone);
+        }
+        self.cursor_mode = mode;
+    }
+    /// Starts recording every input event to `path`, replacing any recording in progress without
+    /// saving it.
+    pub fn start_recording<P: AsRef<Path>>(&mut self, path: P) {
+        self.recording = Some(Recording::new(path, self.step));
+    }
+    /// Stops recording and writes the recorded events to the recording's file.
+    pub fn stop_recording(&mut self) -> Result<(), RecordingErr> {
+        let recording = self.recording.take().ok_or(RecordingErr::NotRecording)?;
+        recording.save()?;
+        log::info!(
+            "Saved {} recorded input events to {}",
+            recording.len(),
+            recording.path().display()
+        );
+        Ok(())
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+    /// Replays the recording at `path` from the next game loop step, ignoring live input until it
+    /// finishes.
+    pub fn start_playback<P: AsRef<Path>>(&mut self, path: P) -> Result<(), RecordingErr> {
+        self.playback = Some(Playback::load(path, self.step)?);
+        Ok(())
+    }
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+    /// Called by the game loop before every step. Feeds the events due from playback and returns
+    /// the actions they sent, then moves on to the next step.
+    pub fn advance_step(&mut self) -> Vec<Action> {
+        let mut actions = Vec::new();
+        if let Some(mut playback) = self.playback.take() {
+            while let Some(event) = playback.pop_due(self.step) {
+                actions.extend(self.process_event(event));
+            }
+            if playback.is_finished() {
+                log::info!("Input playback finished");
+            } else {
+                self.playback = Some(playback);
+            }
+        }
+        self.step += 1;
+        actions
+    }
+    fn process_key(
+        &mut self,
+        logical: Key,
+        physical: PhysicalKey,
+        state: ElementState,
+        repeat: bool,
+        text: Option<SmolStr>,
+    ) -> Option<Action> {
+        if let Some(key) = match &logical {
             Key::Named(named) => key_from_named_key(*named),
             Key::Character(str) => egui::Key::from_name(str),
             _ => None,
         } {
-            let physical_key = match k.physical_key {
+            let physical_key = match physical {
                 PhysicalKey::Code(kc) => key_from_key_code(kc),
                 _ => None,
             };
             self.egui_events.push(egui::Event::Key {
                 key,
                 physical_key,
-                pressed: k.state.is_pressed(),
-                repeat: k.repeat,
+                pressed: state.is_pressed(),
+                repeat,
                 modifiers: self.egui_modifiers(),
             });
         }
-        let bind_out_maybe = 'blk: {
-            match k.logical_key {
-                Key::Named(named) => {
-                    if let Some(bind_out) = self.bindings.0.get(&BindType::Named(named)) {
-                        break 'blk Some(*bind_out);
-                    }
-                }
-                Key::Character(ch) => {
-                    if let Some(bind_out) = self.bindings.0.get(&BindType::Character(ch)) {
-                        break 'blk Some(*bind_out);
-                    }
-                }
-                _ => (),
+        // Shortcuts like ctrl+c are sent as keys only
+        let modifiers = self.egui_modifiers();
+        if let Some(text) = text.filter(|text| {
+            state.is_pressed()
+                && !modifiers.ctrl
+                && !modifiers.command
+                && text.chars().all(is_printable_char)
+        }) {
+            self.egui_events.push(egui::Event::Text(text.to_string()));
+        }
+        if let (PhysicalKey::Code(kc), true) = (physical, state.is_pressed()) {
+            if self.try_rebind(BindType::Key(kc)) {
+                return None;
             }
-            if let PhysicalKey::Code(kc) = k.physical_key {
-                if let Some(bind_out) = self
-                    .bindings
-                    .0
-                    .get(&BindType::KeyMod(kc, self.current_modifiers))
-                {
-                    break 'blk Some(*bind_out);
-                } else if let Some(bind_out) = self.bindings.0.get(&BindType::Key(kc)) {
-                    break 'blk Some(*bind_out);
-                }
+        }
+        let mut binds = Vec::with_capacity(3);
+        match logical {
+            Key::Named(named) => binds.push(BindType::Named(named)),
+            Key::Character(ch) => binds.push(BindType::Character(ch)),
+            _ => (),
+        }
+        if let PhysicalKey::Code(kc) = physical {
+            binds.push(BindType::KeyMod(kc, self.current_modifiers));
+            binds.push(BindType::Key(kc));
+        }
+        for bind in &binds {
+            self.resolved_bindings
+                .set_axis_button(bind, state.is_pressed());
+        }
+        let (bind, id) = if state.is_pressed() {
+            binds.into_iter().find_map(|bind| {
+                let id = *self.resolved_bindings.buttons.get(&bind)?;
+                Some((bind, id))
+            })
+        } else {
+            // Modifiers can change while a key is down, so release whichever action it's holding
+            if let PhysicalKey::Code(kc) = physical {
+                binds.extend(
+                    self.resolved_bindings
+                        .buttons
+                        .keys()
+                        .filter(|bind| matches!(bind, BindType::KeyMod(k, _) if *k == kc))
+                        .cloned(),
+                );
             }
-            None
+            binds.into_iter().find_map(|bind| {
+                let id = *self.resolved_bindings.buttons.get(&bind)?;
+                self.held[id.index()].contains(&bind).then_some((bind, id))
+            })
+        }?;
+        self.handle_bind_out(id, bind, state)
+    }
+    fn process_cursor_moved(&mut self, x: f64, y: f64) {
+        self.last_cusror_pos = (x, y);
+        self.egui_events
+            .push(egui::Event::PointerMoved(Pos2::new(x as f32, y as f32)));
+    }
+    fn process_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let (unit, delta) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (egui::MouseWheelUnit::Line, egui::vec2(x, y)),
+            MouseScrollDelta::PixelDelta(pos) => (
+                egui::MouseWheelUnit::Point,
+                egui::vec2(pos.x as f32, pos.y as f32),
+            ),
         };
-        self.handle_bind_out(bind_out_maybe?, k.state)
+        self.egui_events.push(egui::Event::MouseWheel {
+            unit,
+            delta,
+            modifiers: self.egui_modifiers(),
+        });
     }
-    pub fn handle_cursor_moved(&mut self, position: PhysicalPosition<f64>) {
-        self.last_cusror_pos = (position.x, position.y);
-        self.egui_events.push(egui::Event::PointerMoved(Pos2::new(
-            position.x as f32,
-            position.y as f32,
-        )));
+    fn process_ime(&mut self, ime: Ime) {
+        let event = match ime {
+            Ime::Enabled => egui::ImeEvent::Enabled,
+            Ime::Preedit(text, _) => egui::ImeEvent::Preedit(text),
+            Ime::Commit(text) => egui::ImeEvent::Commit(text),
+            Ime::Disabled => egui::ImeEvent::Disabled,
+        };
+        self.egui_events.push(egui::Event::Ime(event));
     }
-    pub fn handle_mouse_button(
-        &mut self,
-        state: ElementState,
-        button: MouseButton,
-    ) -> Option<Action> {
+    fn process_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused {
+            // Modifiers released while unfocused are never reported
+            self.current_modifiers = ModifiersState::empty();
+        }
+        self.egui_events.push(egui::Event::WindowFocused(focused));
+    }
+    fn process_mouse_button(&mut self, state: ElementState, button: MouseButton) -> Option<Action> {
         if let Some(pointer_button) = mouse_button_to_pointer_button(button) {
             self.egui_events.push(egui::Event::PointerButton {
                 pos: egui::Pos2::new(self.last_cusror_pos.0 as f32, self.last_cusror_pos.1 as f32),
@@ -452,40 +1380,319 @@ impl Input {
                 modifiers: self.egui_modifiers(),
             });
         }
-        if state.is_pressed() {
-            if let Some(bind_out) = self.bindings.0.get(&BindType::MouseButton(button)) {
-                return self.handle_bind_out(*bind_out, state);
+        if state.is_pressed() && self.rebinding.is_some() {
+            if button == MouseButton::Left {
+                self.cancel_rebind();
+            } else {
+                self.try_rebind(BindType::MouseButton(button));
             }
+            return None;
         }
-        None
+        let bind = BindType::MouseButton(button);
+        self.resolved_bindings
+            .set_axis_button(&bind, state.is_pressed());
+        let id = *self.resolved_bindings.buttons.get(&bind)?;
+        self.handle_bind_out(id, bind, state)
     }
-    pub fn handle_mouse_delta(&mut self, delta: (f32, f32)) -> Action {
+    fn process_mouse_delta(&mut self, delta: (f32, f32)) -> Option<Action> {
         self.egui_events
             .push(egui::Event::MouseMoved(egui::Vec2::new(delta.0, delta.1)));
-        Action::Look(delta.0 as f32, delta.1 as f32)
-    }
-    pub fn query_move(&self) -> (f32, f32) {
-        let mut move_amt = (0.0, 0.0);
-
-        if self.move_inputs[0] {
-            move_amt.1 += 1.0;
+        if self.cursor_mode != CursorMode::Locked {
+            return None;
         }
-        if self.move_inputs[1] {
-            move_amt.0 -= 1.0;
+        let axis = self
+            .resolved_bindings
+            .axes
+            .iter()
+            .find(|axis| axis.binding.source == AxisSource::MouseMove)?;
+        let (x, y) = axis.binding.process(delta);
+        Some(Action {
+            id: axis.id,
+            value: ActionValue::Axis2d(x, y),
+        })
+    }
+    fn process_gamepad_event(&mut self, event: gilrs::EventType) -> Option<Action> {
+        let (button, state) = match event {
+            gilrs::EventType::ButtonPressed(button, _) => (button, ElementState::Pressed),
+            gilrs::EventType::ButtonReleased(button, _) => (button, ElementState::Released),
+            gilrs::EventType::ButtonChanged(button, value, _) => {
+                self.gamepad.buttons.insert(button, value);
+                return None;
+            }
+            gilrs::EventType::AxisChanged(axis, value, _) => {
+                self.gamepad.axes.insert(axis, value);
+                return None;
+            }
+            gilrs::EventType::Disconnected => {
+                self.gamepad = GamepadState::default();
+                return None;
+            }
+            _ => return None,
+        };
+        if state.is_pressed() && self.try_rebind(BindType::GamepadButton(button)) {
+            return None;
         }
-        if self.move_inputs[2] {
-            move_amt.0 += 1.0;
+        let bind = BindType::GamepadButton(button);
+        self.resolved_bindings
+            .set_axis_button(&bind, state.is_pressed());
+        let id = *self.resolved_bindings.buttons.get(&bind)?;
+        self.handle_bind_out(id, bind, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(bind: BindType, action: &str) -> ButtonBinding {
+        ButtonBinding {
+            action: action.into(),
+            bind,
         }
-        if self.move_inputs[3] {
-            move_amt.1 -= 1.0;
+    }
+
+    #[test]
+    fn committed_binds_parse() {
+        yml::from_str::<Bindings>(include_str!("../binds.yaml")).unwrap();
+    }
+
+    #[test]
+    fn conflicts_report_duplicates_and_shadowing() {
+        let ctrl_s = BindType::KeyMod(KeyCode::KeyS, ModifiersState::CONTROL);
+        let mut bindings = Bindings::default_binds();
+        bindings.buttons.extend([
+            button(BindType::Key(KeyCode::F3), "Step"),
+            button(ctrl_s.clone(), "Pause"),
+            button(BindType::Key(KeyCode::KeyS), "Step"),
+            // Same action as the key alone, so nothing is lost
+            button(
+                BindType::KeyMod(KeyCode::F10, ModifiersState::SHIFT),
+                "Step",
+            ),
+        ]);
+        assert_eq!(
+            bindings.conflicts(),
+            vec![
+                Conflict::Duplicate {
+                    bind: BindType::Key(KeyCode::F3),
+                    actions: vec!["ToggleInspector".into(), "Step".into()],
+                },
+                Conflict::Duplicate {
+                    bind: BindType::Key(KeyCode::KeyS),
+                    actions: vec!["Step".into(), "Move".into()],
+                },
+                Conflict::Shadowed {
+                    bind: ctrl_s,
+                    action: "Pause".into(),
+                    shadowed: BindType::Key(KeyCode::KeyS),
+                    shadowed_action: "Step".into(),
+                },
+            ]
+        );
+    }
+
+    /// The platform-specific code gilrs attaches to events, which bindings ignore. Codes can't
+    /// be constructed outside gilrs, but every platform's decodes from zeroes.
+    fn code() -> gilrs::ev::Code {
+        bincode::deserialize(&[0; 16]).unwrap()
+    }
+
+    fn gamepad_input(bindings: Bindings) -> Input {
+        let manifest: ActionManifest = yml::from_str(
+            "
+actions:
+- name: Fire
+  kind: Button
+- name: Throttle
+  kind: Axis1d
+- name: Steer
+  kind: Axis1d
+- name: Move
+  kind: Axis2d
+contexts:
+- name: gameplay
+  priority: 0
+  consume: Bound
+  actions: [Fire, Throttle, Steer, Move]
+",
+        )
+        .unwrap();
+        Input::with_bindings(manifest, bindings)
+    }
+
+    fn assert_near((x, y): (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (x - expected.0).abs() < 1e-5 && (y - expected.1).abs() < 1e-5,
+            "{:?} != {expected:?}",
+            (x, y)
+        );
+    }
+
+    #[test]
+    fn gamepad_buttons_send_actions() {
+        let mut input = gamepad_input(Bindings {
+            buttons: vec![button(
+                BindType::GamepadButton(gilrs::Button::RightTrigger2),
+                "Fire",
+            )],
+            axes: Vec::new(),
+        });
+        let fire = input.action_id("Fire").unwrap();
+        let trigger = gilrs::Button::RightTrigger2;
+        assert_eq!(
+            input.handle_gamepad_event(gilrs::EventType::ButtonPressed(trigger, code())),
+            Some(Action {
+                id: fire,
+                value: ActionValue::Pressed
+            })
+        );
+        assert!(input.is_held(fire));
+        assert_eq!(
+            input.handle_gamepad_event(gilrs::EventType::ButtonReleased(trigger, code())),
+            Some(Action {
+                id: fire,
+                value: ActionValue::Released
+            })
+        );
+        assert!(!input.is_held(fire));
+        // Unbound buttons are ignored
+        let south = gilrs::Button::South;
+        assert_eq!(
+            input.handle_gamepad_event(gilrs::EventType::ButtonPressed(south, code())),
+            None
+        );
+    }
+
+    fn key(code: KeyCode, state: ElementState) -> InputEvent {
+        InputEvent::Key {
+            logical: Key::Unidentified(winit::keyboard::NativeKey::Unidentified),
+            physical: PhysicalKey::Code(code),
+            state,
+            repeat: false,
+            text: None,
         }
+    }
+
+    #[test]
+    fn action_stays_held_while_any_binding_is_down() {
+        let mut input = gamepad_input(Bindings {
+            buttons: vec![
+                button(BindType::Key(KeyCode::Space), "Fire"),
+                button(BindType::Key(KeyCode::Enter), "Fire"),
+            ],
+            axes: Vec::new(),
+        });
+        let fire = input.action_id("Fire").unwrap();
+        let pressed = Some(Action {
+            id: fire,
+            value: ActionValue::Pressed,
+        });
+        let released = Some(Action {
+            id: fire,
+            value: ActionValue::Released,
+        });
+        assert_eq!(
+            input.handle_event(key(KeyCode::Space, ElementState::Pressed)),
+            pressed
+        );
+        assert_eq!(
+            input.handle_event(key(KeyCode::Enter, ElementState::Pressed)),
+            None
+        );
+        // Space is released but enter still holds the action
+        assert_eq!(
+            input.handle_event(key(KeyCode::Space, ElementState::Released)),
+            None
+        );
+        assert!(input.is_held(fire));
+        assert_eq!(
+            input.handle_event(key(KeyCode::Space, ElementState::Pressed)),
+            None
+        );
+        assert_eq!(
+            input.handle_event(key(KeyCode::Enter, ElementState::Released)),
+            None
+        );
+        assert_eq!(
+            input.handle_event(key(KeyCode::Space, ElementState::Released)),
+            released
+        );
+        assert!(!input.is_held(fire));
+        assert_eq!(
+            input.handle_event(key(KeyCode::Enter, ElementState::Pressed)),
+            pressed
+        );
+        let transitions = input.take_button_transitions();
+        assert_eq!(transitions[fire.index()].presses, 2);
+        assert_eq!(transitions[fire.index()].releases, 1);
+    }
+
+    #[test]
+    fn mouse_motion_skips_dead_zone_and_curve() {
+        let binding = AxisBinding {
+            sensitivity: 0.5,
+            invert_y: true,
+            dead_zone: 0.5,
+            curve: ResponseCurve::Power(2.0),
+            ..AxisBinding::new("Look", AxisSource::MouseMove)
+        };
+        assert_near(binding.process((12.0, -4.0)), (6.0, 2.0));
+        assert_near(binding.process((0.25, 0.0)), (0.125, 0.0));
+    }
+
+    #[test]
+    fn dead_zones_rescale_triggers_and_axes() {
+        let mut input = gamepad_input(Bindings {
+            buttons: Vec::new(),
+            axes: vec![
+                AxisBinding {
+                    dead_zone: 0.2,
+                    ..AxisBinding::new(
+                        "Throttle",
+                        AxisSource::GamepadTrigger(gilrs::Button::RightTrigger2),
+                    )
+                },
+                AxisBinding {
+                    dead_zone: 0.25,
+                    invert_x: true,
+                    ..AxisBinding::new("Steer", AxisSource::GamepadAxis(gilrs::Axis::LeftStickX))
+                },
+            ],
+        });
+        let throttle = input.action_id("Throttle").unwrap();
+        let steer = input.action_id("Steer").unwrap();
+        // Analog values are polled rather than sent as actions
+        let mut feed = |trigger, stick_x| {
+            let events = [
+                gilrs::EventType::ButtonChanged(gilrs::Button::RightTrigger2, trigger, code()),
+                gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickX, stick_x, code()),
+            ];
+            for event in events {
+                assert_eq!(input.handle_gamepad_event(event), None);
+            }
+            (input.axis1d(throttle), input.axis1d(steer))
+        };
+
+        assert_near(feed(0.1, 0.2), (0.0, 0.0));
+        assert_near(feed(0.6, 0.625), (0.5, -0.5));
+        assert_near(feed(1.0, -1.0), (1.0, 1.0));
+    }
 
-        if move_amt.0 != 0.0 && move_amt.1 != 0.0 {
-            let inv_mag = 1.0 / (move_amt.0 * move_amt.0 + move_amt.1 * move_amt.1);
-            move_amt.0 *= inv_mag;
-            move_amt.1 *= inv_mag;
+    #[test]
+    fn stick_drives_move() {
+        let mut input = gamepad_input(Bindings::default_binds());
+        let move_action = input.action_id("Move").unwrap();
+        for (axis, value) in [
+            (gilrs::Axis::LeftStickX, 0.6),
+            (gilrs::Axis::LeftStickY, -0.8),
+            // Bound to Look, not Move
+            (gilrs::Axis::RightStickX, 1.0),
+        ] {
+            input.handle_gamepad_event(gilrs::EventType::AxisChanged(axis, value, code()));
         }
+        assert_near(input.axis2d(move_action), (0.6, -0.8));
 
-        move_amt
+        input.handle_gamepad_event(gilrs::EventType::Disconnected);
+        assert_near(input.axis2d(move_action), (0.0, 0.0));
     }
 }
@@ -0,0 +1,385 @@
+use ahash::{AHashMap, AHashSet};
+use egui::collapsing_header::CollapsingState;
+use hydrolox_pga3d::prelude as pga;
+use serde_yml as yml;
+
+use crate::{
+    action::{Action, ActionValue},
+    command::Commands,
+    framework::{Components, Entity},
+    game::UIElement,
+    reflect::{reflected_component, ReflectedComponent, REFLECTED_COMPONENTS},
+};
+
+/// A field edited in the inspector, as the component and path to apply it to.
+struct Edit {
+    component: &'static ReflectedComponent,
+    path: String,
+    value: yml::Value,
+}
+
+/// Debug window listing every entity in a transform hierarchy outliner, with the reflected
+/// components of the selected entity editable live. Toggled by its handler's action,
+/// `ToggleInspector` by default.
+#[derive(Debug, Default)]
+pub struct Inspector {
+    open: bool,
+    selected: Option<Entity>,
+    /// Text fields being edited, by path. Only applied once the field loses focus so that
+    /// half-typed names aren't loaded
+    text_buffers: AHashMap<String, String>,
+    /// Euler angles last set on the selected entity's rotation, since motors can't be decomposed into them
+    euler: [f32; 3],
+}
+impl Inspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn select(&mut self, entity: Entity) {
+        if self.selected != Some(entity) {
+            self.selected = Some(entity);
+            self.text_buffers.clear();
+            self.euler = [0.0; 3];
+        }
+    }
+
+    fn outliner_entry(
+        &mut self,
+        ui: &mut egui::Ui,
+        entity: Entity,
+        child_lists: &AHashMap<Entity, Vec<Entity>>,
+    ) {
+        let selected = self.selected == Some(entity);
+        match child_lists.get(&entity) {
+            Some(children) => {
+                CollapsingState::load_with_default_open(
+                    ui.ctx(),
+                    ui.id().with(("outliner", entity)),
+                    false,
+                )
+                .show_header(ui, |ui| {
+                    if ui.selectable_label(selected, entity.to_string()).clicked() {
+                        self.select(entity);
+                    }
+                })
+                .body(|ui| {
+                    for &child in children {
+                        self.outliner_entry(ui, child, child_lists);
+                    }
+                });
+            }
+            None => {
+                if ui.selectable_label(selected, entity.to_string()).clicked() {
+                    self.select(entity);
+                }
+            }
+        }
+    }
+
+    fn outliner(&mut self, ui: &mut egui::Ui, components: &Components) {
+        let all_entities: AHashSet<Entity> = REFLECTED_COMPONENTS
+            .iter()
+            .flat_map(|comp| (comp.entities)(components))
+            .chain(components.uis.read().unwrap().iter().map(|(e, _)| e))
+            .collect();
+
+        let transforms = components.transforms.read().unwrap();
+        let mut roots = Vec::new();
+        let mut child_lists: AHashMap<Entity, Vec<Entity>> = AHashMap::new();
+        for (e, transform) in transforms.iter() {
+            match transform.parent {
+                Some(parent) if transforms.has_component(parent) => {
+                    child_lists.entry(parent).or_default().push(e)
+                }
+                _ => roots.push(e),
+            }
+        }
+        roots.sort();
+        for children in child_lists.values_mut() {
+            children.sort();
+        }
+        let mut others: Vec<Entity> = all_entities
+            .into_iter()
+            .filter(|&e| !transforms.has_component(e))
+            .collect();
+        others.sort();
+        drop(transforms);
+
+        egui::ScrollArea::vertical()
+            .id_salt("outliner")
+            .show(ui, |ui| {
+                for root in roots {
+                    self.outliner_entry(ui, root, &child_lists);
+                }
+                if !others.is_empty() {
+                    ui.separator();
+                    ui.label("Without transform");
+                    for e in others {
+                        if ui
+                            .selectable_label(self.selected == Some(e), e.to_string())
+                            .clicked()
+                        {
+                            self.select(e);
+                        }
+                    }
+                }
+            });
+    }
+
+    /// Shows an editor for `value` and everything nested in it, returning the first edited field.
+    fn value_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        path: String,
+        label: &str,
+        value: &yml::Value,
+    ) -> Option<(String, yml::Value)> {
+        let mut edit = None;
+        match value {
+            yml::Value::Mapping(mapping) => {
+                ui.collapsing(label, |ui| {
+                    for (key, child) in mapping {
+                        let key = key.as_str().unwrap_or_default();
+                        let child_path = join_path(&path, key);
+                        edit = edit
+                            .take()
+                            .or(self.value_editor(ui, child_path, key, child));
+                    }
+                });
+            }
+            yml::Value::Sequence(seq) => {
+                ui.collapsing(format!("{label} [{}]", seq.len()), |ui| {
+                    for (i, child) in seq.iter().enumerate() {
+                        let key = i.to_string();
+                        let child_path = join_path(&path, &key);
+                        edit = edit
+                            .take()
+                            .or(self.value_editor(ui, child_path, &key, child));
+                    }
+                });
+            }
+            yml::Value::Tagged(tagged) => {
+                let tag = tagged.tag.to_string();
+                let tag = tag.trim_start_matches('!');
+                let child_path = join_path(&path, tag);
+                edit = self.value_editor(ui, child_path, &format!("{label}: {tag}"), &tagged.value);
+            }
+            _ => {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    edit = self
+                        .leaf_editor(ui, &path, value)
+                        .map(|v| (path.clone(), v));
+                });
+            }
+        }
+        edit
+    }
+
+    fn leaf_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        path: &str,
+        value: &yml::Value,
+    ) -> Option<yml::Value> {
+        match value {
+            yml::Value::Bool(b) => {
+                let mut b = *b;
+                ui.checkbox(&mut b, "")
+                    .changed()
+                    .then(|| yml::Value::Bool(b))
+            }
+            yml::Value::Number(n) if n.is_f64() => {
+                let mut f = n.as_f64().unwrap();
+                ui.add(egui::DragValue::new(&mut f).speed(0.01))
+                    .changed()
+                    .then(|| yml::Value::from(f))
+            }
+            yml::Value::Number(n) => {
+                let mut i = n.as_i64().unwrap_or_default();
+                ui.add(egui::DragValue::new(&mut i))
+                    .changed()
+                    .then(|| yml::Value::from(i))
+            }
+            yml::Value::String(s) => {
+                let buffer = self
+                    .text_buffers
+                    .entry(path.into())
+                    .or_insert_with(|| s.clone());
+                let response = ui.text_edit_singleline(buffer);
+                if response.lost_focus() {
+                    let buffer = self.text_buffers.remove(path).unwrap();
+                    (&buffer != s).then(|| yml::Value::String(buffer))
+                } else {
+                    if !response.has_focus() {
+                        self.text_buffers.remove(path);
+                    }
+                    None
+                }
+            }
+            yml::Value::Null => {
+                ui.weak("None");
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Translation and rotation controls for a transform's motor.
+    fn motor_editor(&mut self, ui: &mut egui::Ui, motor: pga::Motor) -> Option<pga::Motor> {
+        let translation = motor
+            .factor_translation()
+            .transform(pga::Point::from_position(0.0, 0.0, 0.0));
+        let mut position = [translation.x, translation.y, translation.z];
+        let mut edited = None;
+
+        ui.horizontal(|ui| {
+            ui.label("Translation");
+            let mut changed = false;
+            for axis in &mut position {
+                changed |= ui.add(egui::DragValue::new(axis).speed(0.01)).changed();
+            }
+            if changed {
+                edited = Some(
+                    motor
+                        .factor_rotation()
+                        .combine(pga::Motor::from_translation(
+                            position[0],
+                            position[1],
+                            position[2],
+                        )),
+                );
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Rotation (euler)");
+            let mut changed = false;
+            for angle in &mut self.euler {
+                changed |= ui
+                    .add(egui::DragValue::new(angle).speed(0.01).suffix(" rad"))
+                    .changed();
+            }
+            if changed {
+                let [x, y, z] = self.euler;
+                edited = Some(
+                    pga::Motor::from_euler_angles(x, y, z).combine(motor.factor_translation()),
+                );
+            }
+        });
+
+        edited
+    }
+
+    fn component_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        components: &Components,
+        entity: Entity,
+    ) -> Option<Edit> {
+        let mut edit = None;
+
+        if let Some(transform) = components.transforms.read().unwrap().get(entity) {
+            let motor = transform.motor;
+            ui.collapsing("transform", |ui| {
+                ui.label(match transform.parent {
+                    Some(parent) => format!("Parent: {parent}"),
+                    None => "No parent".into(),
+                });
+                if let Some(motor) = self.motor_editor(ui, motor) {
+                    let value = yml::to_value(motor);
+                    edit = value.ok().map(|value| Edit {
+                        component: reflected_component("transform").unwrap(),
+                        path: "motor".into(),
+                        value,
+                    });
+                }
+            });
+        }
+
+        // Transforms are edited above
+        for component in REFLECTED_COMPONENTS
+            .iter()
+            .filter(|c| c.name != "transform")
+        {
+            let Ok(value) = (component.get)(components, entity, "") else {
+                continue;
+            };
+            if let Some((path, value)) =
+                self.value_editor(ui, String::new(), component.name, &value)
+            {
+                edit = Some(Edit {
+                    component,
+                    path,
+                    value,
+                });
+            }
+        }
+
+        edit
+    }
+}
+impl UIElement for Inspector {
+    fn active(&self) -> bool {
+        self.open
+    }
+    fn handle_action(&mut self, action: Action) {
+        if action.value == ActionValue::Pressed {
+            self.open = !self.open;
+        }
+    }
+    fn show(&mut self, ctx: &egui::Context, components: &Components, commands: &mut Commands) {
+        let mut open = self.open;
+        egui::Window::new("Inspector")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.columns(2, |columns| {
+                    self.outliner(&mut columns[0], components);
+
+                    let ui = &mut columns[1];
+                    let Some(entity) = self.selected else {
+                        ui.weak("No entity selected");
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        ui.heading(format!("Entity {entity}"));
+                        if ui.button("Despawn").clicked() {
+                            commands.despawn_recursive(entity);
+                            self.selected = None;
+                        }
+                    });
+                    egui::ScrollArea::vertical()
+                        .id_salt("components")
+                        .show(ui, |ui| {
+                            if let Some(Edit {
+                                component,
+                                path,
+                                value,
+                            }) = self.component_editor(ui, components, entity)
+                            {
+                                commands.add(move |components, _| {
+                                    if let Err(err) =
+                                        (component.set)(components, entity, &path, value)
+                                    {
+                                        log::error!(
+                                            "Failed to set {}.{path} of entity {entity}: {err}",
+                                            component.name
+                                        );
+                                    }
+                                });
+                            }
+                        });
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.into()
+    } else {
+        format!("{path}.{key}")
+    }
+}
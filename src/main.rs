@@ -1,30 +1,54 @@
 use std::{
-    any::Any, error::Error, num::NonZeroU16, panic, process::ExitCode, sync::{
+    any::Any, error::Error, num::NonZeroU16, panic, path::{Path, PathBuf}, process::ExitCode, sync::{
         mpsc::{channel, Receiver, Sender},
         Arc, RwLock,
     }, thread::{self, JoinHandle}
 };
 
 use clap::Parser;
+use cursor::CursorMode;
 use framework::Components;
-use game::{Game, GameError, GameMessage};
+use game::{Game, GameError, GameInit, GameMessage};
+use gamepad::{GamepadError, GamepadInit, GamepadMessage, Gamepads};
+use headless::Headless;
 use input::Input;
 use log::{error, info};
+use profile::Profiler;
 use render::{Render, RenderError, RenderInit, RenderMessage};
 use winit::{
     application::ApplicationHandler,
-    dpi::{PhysicalSize, Size},
+    dpi::{PhysicalPosition, PhysicalSize, Size},
     event::{DeviceEvent, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy},
     window::Window,
 };
 
+mod action;
+mod buttons;
+mod clock;
+mod command;
+mod console;
+mod context;
+mod cursor;
+mod cvar;
+mod event;
 mod framework;
 mod game;
+mod gamepad;
 mod geometry;
+mod headless;
 mod input;
+mod inspector;
 mod physics;
+mod prefab;
+mod profile;
+mod recording;
+mod reflect;
 mod render;
+mod resource;
+mod scene;
+mod schedule;
+mod settings;
 mod timer;
 
 pub trait SystemMessage {
@@ -120,16 +144,36 @@ fn new_system<T: System>(
     ))
 }
 
+/// Sent to the app from other systems through its event loop proxy
+#[derive(Debug)]
+pub enum AppMessage {
+    SetCursorMode(CursorMode),
+    /// Enables IME with candidates shown at the given text cursor, in physical pixels, or disables
+    /// it if None
+    SetImeArea(Option<egui::Rect>),
+}
+
 #[derive(Debug)]
 struct App {
     window: Option<Arc<Window>>,
     render: Option<SystemData<RenderError, RenderMessage>>,
     game: Option<SystemData<GameError, GameMessage>>,
+    gamepads: Option<SystemData<GamepadError, GamepadMessage>>,
     components: Arc<Components>,
     input: Arc<RwLock<Input>>,
+    profiler: Arc<RwLock<Profiler>>,
+    proxy: EventLoopProxy<AppMessage>,
+    cursor_mode: CursorMode,
+    serial_processes: bool,
+    step_period: f32,
 }
 impl App {
-    fn new() -> Self {
+    fn new(
+        serial_processes: bool,
+        step_period: f32,
+        profiler: Arc<RwLock<Profiler>>,
+        proxy: EventLoopProxy<AppMessage>,
+    ) -> Self {
         let components = Arc::default();
         let input = Arc::new(RwLock::new(match Input::new() {
             Ok(i) => i,
@@ -143,12 +187,18 @@ impl App {
             window: None,
             render: None,
             game: None,
+            gamepads: None,
             components,
             input,
+            profiler,
+            proxy,
+            cursor_mode: CursorMode::default(),
+            serial_processes,
+            step_period,
         }
     }
 }
-impl ApplicationHandler for App {
+impl ApplicationHandler<AppMessage> for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         if self.window.is_none() {
             self.window = Some(
@@ -174,6 +224,7 @@ impl ApplicationHandler for App {
                     res_x: 1280,
                     res_y: 720,
                     max_framerate: Some(NonZeroU16::new(200).unwrap()),
+                    profiler: self.profiler.clone(),
                 },
             ) {
                 Ok(r) => Some(r),
@@ -184,14 +235,39 @@ impl ApplicationHandler for App {
             };
         }
         if self.game.is_none() {
-            self.game = Some(
-                new_system::<Game>(
-                    &self.components,
-                    &self.input,
-                    self.render.as_ref().unwrap().sender.clone(),
-                )
-                .unwrap(),
-            );
+            self.game = match new_system::<Game>(
+                &self.components,
+                &self.input,
+                GameInit {
+                    render_sender: self.render.as_ref().unwrap().sender.clone(),
+                    serial_processes: self.serial_processes,
+                    step_period: self.step_period,
+                    profiler: self.profiler.clone(),
+                    app_proxy: Some(self.proxy.clone()),
+                },
+            ) {
+                Ok(g) => Some(g),
+                Err(err) => {
+                    error!("Failed to init game: {err}");
+                    panic!();
+                }
+            };
+        }
+        if self.gamepads.is_none() {
+            // Gamepads are optional, so the game can run without them
+            self.gamepads = match new_system::<Gamepads>(
+                &self.components,
+                &self.input,
+                GamepadInit {
+                    game_sender: self.game.as_ref().unwrap().sender.clone(),
+                },
+            ) {
+                Ok(g) => Some(g),
+                Err(err) => {
+                    error!("Failed to init gamepads: {err}");
+                    None
+                }
+            };
         }
     }
     fn window_event(
@@ -202,6 +278,29 @@ impl ApplicationHandler for App {
     ) {
         match event {
             WindowEvent::CloseRequested => event_loop.exit(), //Possibly do something else here first (ex saving, asking if sure)
+            WindowEvent::Focused(focused) => {
+                self.input.write().unwrap().handle_focused(focused);
+                // Some platforms drop the cursor grab while unfocused
+                if focused {
+                    self.cursor_mode.apply(self.window.as_ref().unwrap());
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.input
+                    .write()
+                    .unwrap()
+                    .handle_modifiers_changed(modifiers.state());
+            }
+            WindowEvent::Ime(ime) => {
+                self.input.write().unwrap().handle_ime(ime);
+            }
+            WindowEvent::MouseWheel {
+                device_id: _,
+                delta,
+                phase: _,
+            } => {
+                self.input.write().unwrap().handle_mouse_wheel(delta);
+            }
             WindowEvent::KeyboardInput {
                 device_id: _,
                 event: key,
@@ -261,25 +360,48 @@ impl ApplicationHandler for App {
     ) {
         match event {
             DeviceEvent::MouseMotion { delta: (x, y) } => {
-                let action = self
+                let action_maybe = self
                     .input
                     .write()
                     .unwrap()
                     .handle_mouse_delta((x as f32, y as f32));
-                if let Err(err) = self
-                    .game
-                    .as_ref()
-                    .unwrap()
-                    .sender
-                    .send(GameMessage::Input(action))
-                {
-                    error!("Failed to send mouse motion to game: {err}");
-                    panic!();
+                if let Some(action) = action_maybe {
+                    if let Err(err) = self
+                        .game
+                        .as_ref()
+                        .unwrap()
+                        .sender
+                        .send(GameMessage::Input(action))
+                    {
+                        error!("Failed to send mouse motion to game: {err}");
+                        panic!();
+                    }
                 }
             }
             _ => (),
         }
     }
+    fn user_event(&mut self, _: &winit::event_loop::ActiveEventLoop, event: AppMessage) {
+        match event {
+            AppMessage::SetCursorMode(mode) => {
+                self.cursor_mode = mode;
+                if let Some(window) = self.window.as_ref() {
+                    mode.apply(window);
+                }
+            }
+            AppMessage::SetImeArea(area) => {
+                if let Some(window) = self.window.as_ref() {
+                    window.set_ime_allowed(area.is_some());
+                    if let Some(rect) = area {
+                        window.set_ime_cursor_area(
+                            PhysicalPosition::new(rect.min.x, rect.min.y),
+                            PhysicalSize::new(rect.width(), rect.height()),
+                        );
+                    }
+                }
+            }
+        }
+    }
     fn new_events(&mut self, _: &winit::event_loop::ActiveEventLoop, _: winit::event::StartCause) {
         if let Some(render) = self.render.as_ref() {
             if !render.is_thread_active() {
@@ -335,6 +457,58 @@ struct CliArgs {
     /// Output logs to logfile
     #[arg(short, long, default_value_t = false)]
     write_logfile: bool,
+
+    /// Run game processes one at a time in a fixed order, for debugging
+    #[arg(long, default_value_t = false)]
+    serial_processes: bool,
+
+    /// Gameplay steps per second of simulated time
+    #[arg(long, default_value_t = NonZeroU16::new(60).unwrap())]
+    step_rate: NonZeroU16,
+
+    /// Write profiling data to a Chrome trace format JSON file on exit
+    #[arg(long)]
+    chrome_trace: Option<PathBuf>,
+
+    /// Record all input to a file, written on exit
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Replay input recorded with --record instead of reading live input
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Replay without a window or renderer, as fast as possible, then exit
+    #[arg(long, default_value_t = false, requires = "replay")]
+    headless: bool,
+}
+
+/// Replays the recording at `path` with a [`Headless`] game.
+fn replay_headless(path: &Path, step_period: f32) -> ExitCode {
+    let input = match Input::new() {
+        Ok(input) => input,
+        Err(err) => {
+            error!("Failed to init input: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut headless = match Headless::new(input, step_period) {
+        Ok(headless) => headless,
+        Err(err) => {
+            error!("Failed to init game: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match headless.replay(path) {
+        Ok(loops) => {
+            info!("Replayed {} in {loops} game loops", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Failed to load input recording {}: {err}", path.display());
+            ExitCode::FAILURE
+        }
+    }
 }
 
 fn main() -> ExitCode {
@@ -350,7 +524,12 @@ fn main() -> ExitCode {
     info!("Starting Hydrolox");
     info!("Hello World!");
 
-    let event_loop = match EventLoop::new() {
+    let step_period = (args.step_rate.get() as f32).recip();
+    if args.headless {
+        return replay_headless(args.replay.as_ref().unwrap(), step_period);
+    }
+
+    let event_loop = match EventLoop::<AppMessage>::with_user_event().build() {
         Ok(ev) => ev,
         Err(err) => {
             error!("Error when creating event loop: {err}");
@@ -359,11 +538,41 @@ fn main() -> ExitCode {
     };
     event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App::new();
+    let profiler = Arc::new(RwLock::new(Profiler::new()));
+    let mut app = App::new(
+        args.serial_processes,
+        step_period,
+        profiler.clone(),
+        event_loop.create_proxy(),
+    );
+    if let Some(path) = &args.record {
+        app.input.write().unwrap().start_recording(path);
+        info!("Recording input to {}", path.display());
+    }
+    if let Some(path) = &args.replay {
+        if let Err(err) = app.input.write().unwrap().start_playback(path) {
+            error!("Failed to load input recording {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+        info!("Replaying input from {}", path.display());
+    }
     if let Err(err) = event_loop.run_app(&mut app) {
         error!("Error running event loop: {err}");
         return ExitCode::FAILURE;
     }
 
+    if args.record.is_some() {
+        if let Err(err) = app.input.write().unwrap().stop_recording() {
+            error!("Failed to save input recording: {err}");
+        }
+    }
+
+    if let Some(path) = args.chrome_trace {
+        match profiler.read().unwrap().write_chrome_trace(&path) {
+            Ok(()) => info!("Wrote Chrome trace to {}", path.display()),
+            Err(err) => error!("Failed to write Chrome trace: {err}"),
+        }
+    }
+
     ExitCode::SUCCESS
 }
@@ -1,14 +1,18 @@
-use std::sync::{mpsc::Sender, RwLock};
+use std::sync::mpsc::Sender;
 
+use ahash::AHashSet;
 use hydrolox_pga3d::prelude as pga;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    framework::{Component, Components, Comptainer, Entity},
-    game::{Process, ProcessStage, Transform, STEP_PERIOD},
+    clock::GameTime,
+    command::Commands,
+    framework::{CompFlags, Component, Components, Comptainer, Entity, EntityMap},
+    game::{ComponentAccess, Process, ProcessStage, Transform},
     geometry::{Vec3, AABB},
-    input::Input,
+    reflect::impl_reflect,
     render::RenderMessage,
+    resource::Resources,
 };
 
 fn gjk_support_verts<I: IntoIterator<Item = Vec3>>(dir: Vec3, verts: I) -> Vec3
@@ -104,6 +108,12 @@ pub enum ColliderShape {
     Box { dimensions: Vec3 },
     Sphere { radius: f32 },
 }
+impl_reflect!(
+    enum ColliderShape {
+        Box { dimensions: Vec3 },
+        Sphere { radius: f32 },
+    }
+);
 impl Component for ColliderShape {}
 impl ColliderShape {
     fn gjk_support(&self, dir: Vec3, motor: &pga::Motor) -> Vec3 {
@@ -150,6 +160,11 @@ pub struct Collider {
     pub events: Vec<CollisionEvent>,
     pub collision_type: CollisionType,
 }
+impl_reflect!(Collider {
+    shapes: Vec<Entity>,
+    events: Vec<CollisionEvent>,
+    collision_type: CollisionType,
+});
 impl Collider {
     fn aabb(
         &self,
@@ -159,12 +174,9 @@ impl Collider {
         let mut min = Vec3::MAX;
         let mut max = Vec3::MIN;
 
-        for (motor, col_shape) in self.shapes.iter().map(|&e| {
-            let transform = transforms.get(e).unwrap();
-            (
-                transform.global_motor(transforms),
-                col_shapes.get(e).unwrap(),
-            )
+        for (motor, col_shape) in self.shapes.iter().filter_map(|&e| {
+            let transform = transforms.get(e)?;
+            Some((transform.global_motor(transforms).ok()?, col_shapes.get(e)?))
         }) {
             let aabb = col_shape.aabb(&motor);
             min = aabb.min.min_components(min);
@@ -176,6 +188,7 @@ impl Collider {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PhysicsBody {
     pub mass: f32,
     pub angular_inertia: f32,
@@ -186,11 +199,12 @@ pub struct PhysicsBody {
     pub collision: Option<Collider>,
 }
 impl PhysicsBody {
-    pub fn apply_force(&mut self, force: Vec3, location: Vec3) {
-        self.apply_impulse(force * STEP_PERIOD, location);
+    /// Applies `force` for `dt` seconds, usually [`GameTime::delta`].
+    pub fn apply_force(&mut self, force: Vec3, location: Vec3, dt: f32) {
+        self.apply_impulse(force * dt, location);
     }
-    pub fn apply_force_central(&mut self, force: Vec3) {
-        self.apply_impulse_central(force * STEP_PERIOD);
+    pub fn apply_force_central(&mut self, force: Vec3, dt: f32) {
+        self.apply_impulse_central(force * dt);
     }
     pub fn apply_impulse(&mut self, impulse: Vec3, location: Vec3) {
         self.angular_imp += location.cross(impulse);
@@ -215,38 +229,81 @@ impl Default for PhysicsBody {
         }
     }
 }
-impl Component for PhysicsBody {}
+impl_reflect!(PhysicsBody {
+    mass: f32,
+    angular_inertia: f32,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    linear_imp: Vec3,
+    angular_imp: Vec3,
+    collision: Option<Collider>,
+});
+impl Component for PhysicsBody {
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(collider) = &mut self.collision {
+            for shape in &mut collider.shapes {
+                *shape = map.map(*shape);
+            }
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct PhysicsProcess {}
+/// Moves physics bodies. Bodies and collision shapes missing a transform or shape are skipped,
+/// and reported once per entity.
+#[derive(Debug, Default)]
+pub struct PhysicsProcess {
+    reported: AHashSet<Entity>,
+}
+impl PhysicsProcess {
+    fn report(&mut self, entity: Entity, problem: &str) {
+        if self.reported.insert(entity) {
+            log::error!("Skipping physics for entity {entity}: {problem}");
+        }
+    }
+}
 impl Process for PhysicsProcess {
+    fn name(&self) -> &'static str {
+        "Physics"
+    }
     fn stage(&self) -> ProcessStage {
         ProcessStage::Physics
     }
+    fn access(&self) -> ComponentAccess {
+        ComponentAccess::new(
+            CompFlags::COLLISION_SHAPES | CompFlags::GLOBAL_TRANSFORMS,
+            CompFlags::TRANSFORMS | CompFlags::PHYSICS_BODIES,
+        )
+        .reads_resource::<GameTime>()
+    }
 
-    fn new(_: &Components, _: &Sender<RenderMessage>) -> Self
+    fn new(_: &Components, _: &mut Resources, _: &Sender<RenderMessage>) -> Self
     where
         Self: Sized,
     {
-        Self {}
+        Self::default()
     }
-    fn step(&mut self, components: &Components, _: &RwLock<Input>) {
+    fn step(&mut self, components: &Components, resources: &Resources, _: &mut Commands) {
+        let dt = resources.resource::<GameTime>().unwrap().delta;
         let mut transforms = components.transforms.write().unwrap();
         let mut physics_bodies = components.physics_bodies.write().unwrap();
         let collider_shapes = components.collision_shapes.read().unwrap();
+        let global_transforms = components.global_transforms.read().unwrap();
 
         let mut octree = Octree::new();
 
         for (e, physics_body) in physics_bodies.iter_mut() {
-            let transform = transforms.get_mut(e).unwrap();
+            let Some(transform) = transforms.get_mut(e) else {
+                self.report(e, "physics body has no transform");
+                continue;
+            };
 
             physics_body.linear_velocity += physics_body.linear_imp / physics_body.mass;
-            let delta_pos = physics_body.linear_velocity * STEP_PERIOD;
+            let delta_pos = physics_body.linear_velocity * dt;
             physics_body.linear_imp = Vec3::ZERO;
 
             physics_body.angular_velocity +=
                 physics_body.angular_imp / physics_body.angular_inertia;
-            let delta_rot = physics_body.angular_velocity * STEP_PERIOD;
+            let delta_rot = physics_body.angular_velocity * dt;
             physics_body.angular_imp = Vec3::ZERO;
 
             let prev_motor = transform.motor;
@@ -259,16 +316,27 @@ impl Process for PhysicsProcess {
 
             if let Some(collider) = &physics_body.collision {
                 if !collider.shapes.is_empty() {
-                    let parent_motor = transform.clone().parent_motor(&transforms);
+                    // Problems with the hierarchy are reported by the transform process
+                    let parent_motor = transform
+                        .parent
+                        .and_then(|parent| global_transforms.get(parent))
+                        .map(|global| global.motor);
                     let prev_global = parent_motor.map_or(prev_motor, |m| m.combine(prev_motor));
                     let next_global = parent_motor.map_or(next_motor, |m| m.combine(next_motor));
                     let mut min = Vec3::MAX;
                     let mut max = Vec3::MIN;
 
-                    for (shape_motor, col_shape) in collider.shapes.iter().map(|&e| {
-                        let col_transform = transforms.get(e).unwrap();
-                        (col_transform.motor, collider_shapes.get(e).unwrap())
-                    }) {
+                    let mut shapes = Vec::with_capacity(collider.shapes.len());
+                    for &shape in &collider.shapes {
+                        match (transforms.get(shape), collider_shapes.get(shape)) {
+                            (Some(transform), Some(col_shape)) => {
+                                shapes.push((transform.motor, col_shape))
+                            }
+                            (None, _) => self.report(shape, "collision shape has no transform"),
+                            (_, None) => self.report(shape, "collider has no collision shape"),
+                        }
+                    }
+                    for (shape_motor, col_shape) in shapes {
                         let aabb = col_shape.aabb(&next_global.combine(shape_motor));
                         min = aabb.min.min_components(min);
                         max = aabb.max.max_components(max);
@@ -0,0 +1,292 @@
+use std::{error::Error, fmt::Display, fs::File, num::NonZeroU64};
+
+use ahash::AHashMap;
+use hydrolox_pga3d::prelude as pga;
+use serde::Deserialize;
+use serde_yml as yml;
+
+use crate::{
+    framework::{Components, Entity, EntityMap},
+    game::Transform,
+    reflect::{self, ReflectErr},
+};
+
+#[derive(Debug)]
+pub enum PrefabErr {
+    Yaml(yml::Error),
+    IO(std::io::Error),
+    Reflect(ReflectErr),
+    UnknownEntity(String),
+    BadOverride(String),
+}
+impl Display for PrefabErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(err) => writeln!(f, "Yaml error: {err}"),
+            Self::IO(err) => writeln!(f, "IO error: {err}"),
+            Self::Reflect(err) => write!(f, "{err}"),
+            Self::UnknownEntity(name) => writeln!(f, "No entity named \"{name}\" in prefab"),
+            Self::BadOverride(path) => writeln!(f, "Invalid override path \"{path}\""),
+        }
+    }
+}
+impl Error for PrefabErr {}
+impl From<yml::Error> for PrefabErr {
+    fn from(value: yml::Error) -> Self {
+        Self::Yaml(value)
+    }
+}
+impl From<ReflectErr> for PrefabErr {
+    fn from(value: ReflectErr) -> Self {
+        Self::Reflect(value)
+    }
+}
+impl From<std::io::Error> for PrefabErr {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+/// Per-instance field overrides, applied on top of the prefab's components before they are created.
+///
+/// Paths take the form `entity.component.field.subfield`, ex. `monkey.physics_body.angular_velocity.y`.
+#[derive(Debug, Clone, Default)]
+pub struct PrefabOverrides(Vec<(String, yml::Value)>);
+impl PrefabOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with(mut self, path: impl Into<String>, value: impl Into<yml::Value>) -> Self {
+        self.0.push((path.into(), value.into()));
+        self
+    }
+}
+
+fn identity_motor() -> pga::Motor {
+    pga::Motor::IDENTITY
+}
+
+/// The transform of a prefab entity. Its parent comes from the prefab's hierarchy instead.
+#[derive(Debug, Deserialize)]
+struct PrefabTransform {
+    #[serde(default = "identity_motor")]
+    motor: pga::Motor,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PrefabEntity {
+    name: String,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    components: yml::Mapping,
+}
+
+/// A reusable template describing a hierarchy of entities and their components.
+///
+/// Every prefab entity gets a [`Transform`], parented according to the hierarchy. Entities
+/// referenced from inside components are written by name with an `!entity` tag, ex.
+/// `shapes: [!entity hull]`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Prefab {
+    entities: Vec<PrefabEntity>,
+}
+impl Prefab {
+    /// Loads the prefab at `assets/prefabs/<name>.yaml`.
+    pub fn load(name: &str) -> Result<Self, PrefabErr> {
+        let file = File::open(format!("assets/prefabs/{name}.yaml"))?;
+        let prefab: Self = yml::from_reader(file)?;
+        for entity in &prefab.entities {
+            if let Some(parent) = &entity.parent {
+                if !prefab.entities.iter().any(|e| &e.name == parent) {
+                    return Err(PrefabErr::UnknownEntity(parent.clone()));
+                }
+            }
+        }
+        Ok(prefab)
+    }
+
+    fn apply_override(
+        entities: &mut [PrefabEntity],
+        path: &str,
+        value: yml::Value,
+    ) -> Result<(), PrefabErr> {
+        let mut segments = path.split('.');
+        let entity_name = segments
+            .next()
+            .ok_or_else(|| PrefabErr::BadOverride(path.into()))?;
+        let entity = entities
+            .iter_mut()
+            .find(|e| e.name == entity_name)
+            .ok_or_else(|| PrefabErr::UnknownEntity(entity_name.into()))?;
+
+        let mut mapping = &mut entity.components;
+        let mut key = segments
+            .next()
+            .ok_or_else(|| PrefabErr::BadOverride(path.into()))?;
+        for next_key in segments {
+            let child = mapping
+                .entry(key.into())
+                .or_insert_with(|| yml::Value::Mapping(yml::Mapping::new()));
+            mapping = child
+                .as_mapping_mut()
+                .ok_or_else(|| PrefabErr::BadOverride(path.into()))?;
+            key = next_key;
+        }
+        mapping.insert(key.into(), value);
+
+        Ok(())
+    }
+
+    /// Replaces every `!entity <name>` in `value` with the local id of the named entity.
+    fn resolve_entity_refs(
+        value: &mut yml::Value,
+        local_ids: &AHashMap<&str, u64>,
+    ) -> Result<(), PrefabErr> {
+        match value {
+            yml::Value::Tagged(tagged) if tagged.tag == "entity" => {
+                let name = tagged.value.as_str().unwrap_or_default();
+                let &id = local_ids
+                    .get(name)
+                    .ok_or_else(|| PrefabErr::UnknownEntity(name.into()))?;
+                *value = id.into();
+            }
+            yml::Value::Tagged(tagged) => Self::resolve_entity_refs(&mut tagged.value, local_ids)?,
+            yml::Value::Sequence(seq) => {
+                for value in seq {
+                    Self::resolve_entity_refs(value, local_ids)?;
+                }
+            }
+            yml::Value::Mapping(map) => {
+                for (_, value) in map {
+                    Self::resolve_entity_refs(value, local_ids)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Creates a new copy of the prefab's entities, with root entities placed at `motor`.
+    /// Returns the new entities in the same order they are listed in the prefab.
+    pub fn instantiate(
+        &self,
+        components: &Components,
+        motor: pga::Motor,
+        overrides: &PrefabOverrides,
+    ) -> Result<Vec<Entity>, PrefabErr> {
+        let mut entities = self.entities.clone();
+        for (path, value) in &overrides.0 {
+            Self::apply_override(&mut entities, path, value.clone())?;
+        }
+
+        let mut map = EntityMap::new();
+        let new_entities: Vec<Entity> = (1..=entities.len() as u64)
+            .map(|local_id| {
+                let local = Entity::from_raw(NonZeroU64::new(local_id).unwrap());
+                map.get_or_insert(local)
+            })
+            .collect();
+        let local_ids: AHashMap<&str, u64> = entities
+            .iter()
+            .zip(1..)
+            .map(|(e, local_id)| (e.name.as_str(), local_id))
+            .collect();
+        let name_to_entity: AHashMap<&str, Entity> = entities
+            .iter()
+            .zip(&new_entities)
+            .map(|(e, &new)| (e.name.as_str(), new))
+            .collect();
+
+        type Spawn = Box<dyn FnOnce(&Components)>;
+        // Deserialize everything first so a bad component doesn't leave a partial instance behind
+        let mut to_spawn: Vec<Spawn> = Vec::new();
+        for (prefab_entity, &entity) in entities.iter().zip(&new_entities) {
+            let mut prefab_motor = pga::Motor::IDENTITY;
+            for (name, value) in &prefab_entity.components {
+                let name = name.as_str().unwrap_or_default();
+                if name == "transform" {
+                    prefab_motor = yml::from_value::<PrefabTransform>(value.clone())?.motor;
+                    continue;
+                }
+                let mut value = value.clone();
+                Self::resolve_entity_refs(&mut value, &local_ids)?;
+                let comp = (reflect::reflected_component(name)?.deserialize)(value, &map)?;
+                to_spawn.push(Box::new(move |components| comp.add(components, entity)));
+            }
+
+            let transform = if let Some(parent) = &prefab_entity.parent {
+                Transform::new(Some(name_to_entity[parent.as_str()]), prefab_motor)
+            } else {
+                Transform::new(None, motor.combine(prefab_motor))
+            };
+            to_spawn.push(Box::new(move |components| {
+                _ = components
+                    .transforms
+                    .write()
+                    .unwrap()
+                    .add_component(entity, transform);
+            }));
+        }
+
+        for spawn in to_spawn {
+            spawn(components);
+        }
+
+        Ok(new_entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_refs_resolve_by_name() {
+        let prefab: Prefab = yml::from_str(
+            "
+entities:
+  - name: ship
+    components:
+      physics_body:
+        collision:
+          shapes: [!entity hull]
+          events: []
+          collision_type: Discrete
+  - name: hull
+    parent: ship
+    components:
+      collision_shape: !Sphere
+        radius: 1.0
+",
+        )
+        .unwrap();
+        let components = Components::default();
+        let entities = prefab
+            .instantiate(&components, pga::Motor::IDENTITY, &PrefabOverrides::new())
+            .unwrap();
+        let [ship, hull] = entities[..] else {
+            panic!("expected 2 entities, got {entities:?}");
+        };
+        let bodies = components.physics_bodies.read().unwrap();
+        let collider = bodies.get(ship).unwrap().collision.as_ref().unwrap();
+        assert_eq!(collider.shapes, [hull]);
+        assert!(components
+            .collision_shapes
+            .read()
+            .unwrap()
+            .has_component(hull));
+        let transforms = components.transforms.read().unwrap();
+        assert_eq!(transforms.get(hull).unwrap().parent, Some(ship));
+
+        let unknown = prefab.instantiate(
+            &components,
+            pga::Motor::IDENTITY,
+            &PrefabOverrides::new().with(
+                "ship.physics_body.collision.shapes",
+                yml::from_str::<yml::Value>("[!entity wing]").unwrap(),
+            ),
+        );
+        assert!(matches!(unknown, Err(PrefabErr::UnknownEntity(name)) if name == "wing"));
+    }
+}
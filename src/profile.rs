@@ -0,0 +1,273 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    action::{Action, ActionValue},
+    command::Commands,
+    framework::Components,
+    game::UIElement,
+};
+
+/// Number of samples kept per track for the overlay graphs
+pub const HISTORY_LEN: usize = 240;
+/// Number of spans kept for trace dumps, about a minute of frames
+const MAX_SPANS: usize = 1 << 18;
+
+#[derive(Debug, Clone)]
+struct Span {
+    category: &'static str,
+    name: &'static str,
+    thread: usize,
+    /// Time since the profiler was created
+    start: Duration,
+    duration: Duration,
+}
+
+/// Recent durations of one named section of code.
+#[derive(Debug)]
+pub struct Track {
+    pub category: &'static str,
+    pub name: &'static str,
+    /// Durations in milliseconds, oldest first
+    samples: VecDeque<f32>,
+}
+impl Track {
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+    pub fn latest(&self) -> f32 {
+        self.samples.back().copied().unwrap_or_default()
+    }
+    pub fn average(&self) -> f32 {
+        self.samples.iter().sum::<f32>() / self.samples.len().max(1) as f32
+    }
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// Collects timings from the game and render systems, shared between them as a resource.
+#[derive(Debug)]
+pub struct Profiler {
+    epoch: Instant,
+    spans: VecDeque<Span>,
+    tracks: Vec<Track>,
+    threads: Vec<String>,
+}
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            spans: VecDeque::new(),
+            tracks: Vec::new(),
+            threads: Vec::new(),
+        }
+    }
+}
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Records a section of code on the current thread that started at `start` and took `duration`.
+    pub fn record(
+        &mut self,
+        category: &'static str,
+        name: &'static str,
+        start: Instant,
+        duration: Duration,
+    ) {
+        let current = std::thread::current();
+        let thread_name = current.name().unwrap_or("Unnamed thread");
+        let thread = match self.threads.iter().position(|t| t == thread_name) {
+            Some(thread) => thread,
+            None => {
+                self.threads.push(thread_name.into());
+                self.threads.len() - 1
+            }
+        };
+
+        if self.spans.len() == MAX_SPANS {
+            self.spans.pop_front();
+        }
+        self.spans.push_back(Span {
+            category,
+            name,
+            thread,
+            start: start.saturating_duration_since(self.epoch),
+            duration,
+        });
+
+        let track = match self
+            .tracks
+            .iter_mut()
+            .position(|t| t.category == category && t.name == name)
+        {
+            Some(pos) => &mut self.tracks[pos],
+            None => {
+                self.tracks.push(Track {
+                    category,
+                    name,
+                    samples: VecDeque::with_capacity(HISTORY_LEN),
+                });
+                self.tracks.last_mut().unwrap()
+            }
+        };
+        if track.samples.len() == HISTORY_LEN {
+            track.samples.pop_front();
+        }
+        track.samples.push_back(duration.as_secs_f32() * 1000.0);
+    }
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+    /// Writes every recorded span to `path` in the Chrome trace event format, which can be opened in
+    /// `chrome://tracing` or Perfetto.
+    pub fn write_chrome_trace<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{{\"traceEvents\":[")?;
+        for (tid, thread) in self.threads.iter().enumerate() {
+            writeln!(
+                file,
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{tid},\"args\":{{\"name\":{}}}}},",
+                json_string(thread)
+            )?;
+        }
+        for (i, span) in self.spans.iter().enumerate() {
+            let separator = if i + 1 < self.spans.len() { "," } else { "" };
+            writeln!(
+                file,
+                "{{\"name\":{},\"cat\":{},\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{}}}{separator}",
+                json_string(span.name),
+                json_string(span.category),
+                span.start.as_secs_f64() * 1e6,
+                span.duration.as_secs_f64() * 1e6,
+                span.thread,
+            )?;
+        }
+        writeln!(file, "]}}")?;
+        file.flush()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Overlay showing frame time graphs for every profiled track. Toggled by its handler's action,
+/// `ToggleProfiler` by default.
+#[derive(Debug)]
+pub struct ProfilerOverlay {
+    profiler: Arc<RwLock<Profiler>>,
+    /// Length of a game step in seconds, marked on the graphs
+    step_period: f32,
+    open: bool,
+}
+impl ProfilerOverlay {
+    pub fn new(profiler: Arc<RwLock<Profiler>>, step_period: f32) -> Self {
+        Self {
+            profiler,
+            step_period,
+            open: false,
+        }
+    }
+}
+impl UIElement for ProfilerOverlay {
+    fn active(&self) -> bool {
+        self.open
+    }
+    fn handle_action(&mut self, action: Action) {
+        if action.value == ActionValue::Pressed {
+            self.open = !self.open;
+        }
+    }
+    fn show(&mut self, ctx: &egui::Context, _: &Components, _: &mut Commands) {
+        let mut open = self.open;
+        egui::Window::new("Profiler")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if ui.button("Dump Chrome trace").clicked() {
+                    let path = format!(
+                        "trace-{}.json",
+                        std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs()
+                    );
+                    match self.profiler.read().unwrap().write_chrome_trace(&path) {
+                        Ok(()) => log::info!("Wrote Chrome trace to {path}"),
+                        Err(err) => log::error!("Failed to write Chrome trace: {err}"),
+                    }
+                }
+                ui.separator();
+
+                let profiler = self.profiler.read().unwrap();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("profiler tracks")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for track in profiler.tracks() {
+                                ui.label(format!("{}: {}", track.category, track.name));
+                                ui.monospace(format!(
+                                    "{:6.2}ms avg {:6.2}ms max",
+                                    track.average(),
+                                    track.max()
+                                ));
+                                graph(ui, track, self.step_period);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// Draws the track's samples as a line graph, with a line marking one game step.
+fn graph(ui: &mut egui::Ui, track: &Track, step_period: f32) {
+    let step_ms = step_period * 1000.0;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 32.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let scale = track.max().max(step_ms);
+    let to_y = |ms: f32| rect.bottom() - (ms / scale) * rect.height();
+    painter.hline(
+        rect.x_range(),
+        to_y(step_ms),
+        egui::Stroke::new(1.0, egui::Color32::DARK_RED),
+    );
+
+    let dx = rect.width() / (HISTORY_LEN - 1) as f32;
+    let offset = (HISTORY_LEN - track.samples.len()) as f32 * dx;
+    let points: Vec<egui::Pos2> = track
+        .samples()
+        .enumerate()
+        .map(|(i, ms)| egui::pos2(rect.left() + offset + i as f32 * dx, to_y(ms)))
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.0, ui.visuals().text_color()),
+    ));
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::InputEvent;
+
+#[derive(Debug)]
+pub enum RecordingErr {
+    Binary(bincode::Error),
+    IO(std::io::Error),
+    /// Recording was stopped without having been started
+    NotRecording,
+}
+impl Display for RecordingErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Binary(err) => writeln!(f, "Binary encoding error: {err}"),
+            Self::IO(err) => writeln!(f, "IO error: {err}"),
+            Self::NotRecording => writeln!(f, "Input isn't being recorded"),
+        }
+    }
+}
+impl Error for RecordingErr {}
+impl From<bincode::Error> for RecordingErr {
+    fn from(value: bincode::Error) -> Self {
+        Self::Binary(value)
+    }
+}
+impl From<std::io::Error> for RecordingErr {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+/// An input event and the game loop step it reached [`Input`](crate::input::Input) before,
+/// counted from the start of the recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub step: u64,
+    pub event: InputEvent,
+}
+
+/// Input events being recorded, written to `path` when recording stops.
+#[derive(Debug)]
+pub struct Recording {
+    path: PathBuf,
+    /// Game loop step the recording started at
+    start_step: u64,
+    events: Vec<RecordedEvent>,
+}
+impl Recording {
+    pub fn new<P: AsRef<Path>>(path: P, start_step: u64) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            start_step,
+            events: Vec::new(),
+        }
+    }
+    pub fn push(&mut self, step: u64, event: InputEvent) {
+        self.events.push(RecordedEvent {
+            step: step - self.start_step,
+            event,
+        });
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    pub fn save(&self) -> Result<(), RecordingErr> {
+        let file = BufWriter::new(File::create(&self.path)?);
+        bincode::serialize_into(file, &self.events)?;
+        Ok(())
+    }
+}
+
+/// A recording being replayed, feeding each event to [`Input`](crate::input::Input) at the same
+/// game loop step relative to the start of playback as it was recorded at.
+#[derive(Debug)]
+pub struct Playback {
+    /// Game loop step playback started at
+    start_step: u64,
+    events: VecDeque<RecordedEvent>,
+}
+impl Playback {
+    pub fn load<P: AsRef<Path>>(path: P, start_step: u64) -> Result<Self, RecordingErr> {
+        let file = BufReader::new(File::open(path)?);
+        let events: Vec<RecordedEvent> = bincode::deserialize_from(file)?;
+        Ok(Self {
+            start_step,
+            events: events.into(),
+        })
+    }
+    /// Removes and returns the next event if it's due by the game loop step `step`.
+    pub fn pop_due(&mut self, step: u64) -> Option<InputEvent> {
+        if self.events.front()?.step <= step - self.start_step {
+            self.events.pop_front().map(|recorded| recorded.event)
+        } else {
+            None
+        }
+    }
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
@@ -0,0 +1,262 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yml as yml;
+
+use crate::{
+    framework::{Components, Entity, EntityMap, StoredComponent},
+    game, physics, render,
+};
+
+#[derive(Debug)]
+pub enum ReflectErr {
+    Yaml(yml::Error),
+    UnknownField(String),
+    UnknownComponent(String),
+    MissingComponent(Entity),
+}
+impl Display for ReflectErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(err) => writeln!(f, "Yaml error: {err}"),
+            Self::UnknownField(path) => writeln!(f, "No field at path \"{path}\""),
+            Self::UnknownComponent(name) => writeln!(f, "Unknown component \"{name}\""),
+            Self::MissingComponent(entity) => {
+                writeln!(f, "Entity {entity} doesn't have the component")
+            }
+        }
+    }
+}
+impl Error for ReflectErr {}
+impl From<yml::Error> for ReflectErr {
+    fn from(value: yml::Error) -> Self {
+        Self::Yaml(value)
+    }
+}
+
+/// Name and type of one field of a [`Reflect`] type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub type_name: &'static str,
+}
+
+/// Steps into a mapping key, sequence index or enum variant.
+fn child_mut<'a>(value: &'a mut yml::Value, key: &str) -> Option<&'a mut yml::Value> {
+    match value {
+        yml::Value::Sequence(seq) => seq.get_mut(key.parse::<usize>().ok()?),
+        yml::Value::Tagged(tagged) => (tagged.tag == key).then_some(&mut tagged.value),
+        yml::Value::Mapping(map) => map.get_mut(key),
+        _ => None,
+    }
+}
+
+/// Generic access to the fields of a data type, for tooling such as editors and the console.
+///
+/// Paths are dot separated field names, sequence indices or enum variants, ex.
+/// `angular_velocity.y` or `Box.dimensions`. Values go through the type's serde representation, so
+/// they look the same as they do in scene and prefab files.
+pub trait Reflect: Serialize + DeserializeOwned {
+    /// The top level fields. For enums, the fields of every variant are listed by path, ex.
+    /// `Box.dimensions`.
+    const FIELDS: &'static [Field];
+
+    fn get_path(&self, path: &str) -> Result<yml::Value, ReflectErr> {
+        let mut value = yml::to_value(self)?;
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            value = child_mut(&mut value, key)
+                .map(std::mem::take)
+                .ok_or_else(|| ReflectErr::UnknownField(path.into()))?;
+        }
+        Ok(value)
+    }
+    /// Replaces the value at `path`, which must already exist.
+    fn set_path(&mut self, path: &str, new_value: yml::Value) -> Result<(), ReflectErr>
+    where
+        Self: Sized,
+    {
+        let mut root = yml::to_value(&*self)?;
+        let mut value = &mut root;
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            value = child_mut(value, key).ok_or_else(|| ReflectErr::UnknownField(path.into()))?;
+        }
+        *value = new_value;
+        *self = yml::from_value(root)?;
+        Ok(())
+    }
+}
+
+/// Implements [`Reflect`] for a struct or enum from a list of all its fields, which must be
+/// invoked in the same module as the type. Fails to compile if the list doesn't match the type's
+/// fields. Struct fields listed after `skip` are left out, ex. for transient state that should also
+/// be skipped by serde.
+macro_rules! impl_reflect {
+    (
+        $name:ident { $($field:ident: $ty:ty),* $(,)? $(; skip $($skipped:ident),+ $(,)?)? }
+    ) => {
+        impl $crate::reflect::Reflect for $name {
+            const FIELDS: &'static [$crate::reflect::Field] = &[$(
+                $crate::reflect::Field {
+                    name: stringify!($field),
+                    type_name: stringify!($ty),
+                }
+            ),*];
+        }
+        const _: fn(&$name) = |value| {
+            let $name { $($field,)* $($($skipped: _),+)? } = value;
+            $(let _: &$ty = $field;)*
+        };
+    };
+    (enum $name:ident { $($variant:ident { $($field:ident: $ty:ty),* $(,)? }),* $(,)? }) => {
+        impl $crate::reflect::Reflect for $name {
+            const FIELDS: &'static [$crate::reflect::Field] = &[$($(
+                $crate::reflect::Field {
+                    name: concat!(stringify!($variant), ".", stringify!($field)),
+                    type_name: stringify!($ty),
+                }
+            ),*),*];
+        }
+        const _: fn(&$name) = |value| match value {
+            $($name::$variant { $($field),* } => {
+                $(let _: &$ty = $field;)*
+            })*
+        };
+    };
+}
+pub(crate) use impl_reflect;
+
+type AddComponent = Box<dyn FnOnce(&Components, Entity)>;
+
+/// A component deserialized by a [`ReflectedComponent`], waiting to be added to an entity.
+pub struct DeserializedComponent(AddComponent);
+impl DeserializedComponent {
+    /// Adds the component to `entity`, replacing any it already has.
+    pub fn add(self, components: &Components, entity: Entity) {
+        (self.0)(components, entity)
+    }
+}
+
+/// Type-erased access to a reflected comptainer in [`Components`].
+#[derive(Debug, Clone, Copy)]
+pub struct ReflectedComponent {
+    /// Same name the component uses in prefab files
+    pub name: &'static str,
+    pub fields: &'static [Field],
+    pub entities: fn(&Components) -> Vec<Entity>,
+    pub get: fn(&Components, Entity, &str) -> Result<yml::Value, ReflectErr>,
+    pub set: fn(&Components, Entity, &str, yml::Value) -> Result<(), ReflectErr>,
+    /// Deserializes a whole component, remapping the entities it refers to with the map
+    pub deserialize: fn(yml::Value, &EntityMap) -> Result<DeserializedComponent, ReflectErr>,
+}
+impl ReflectedComponent {
+    const fn of<T: StoredComponent + Reflect>(name: &'static str) -> Self {
+        Self {
+            name,
+            fields: T::FIELDS,
+            entities: entities::<T>,
+            get: get::<T>,
+            set: set::<T>,
+            deserialize: deserialize::<T>,
+        }
+    }
+}
+
+fn entities<T: StoredComponent>(components: &Components) -> Vec<Entity> {
+    T::comptainer(components)
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(e, _)| e)
+        .collect()
+}
+fn get<T: StoredComponent + Reflect>(
+    components: &Components,
+    entity: Entity,
+    path: &str,
+) -> Result<yml::Value, ReflectErr> {
+    T::comptainer(components)
+        .read()
+        .unwrap()
+        .get(entity)
+        .ok_or(ReflectErr::MissingComponent(entity))?
+        .get_path(path)
+}
+fn set<T: StoredComponent + Reflect>(
+    components: &Components,
+    entity: Entity,
+    path: &str,
+    value: yml::Value,
+) -> Result<(), ReflectErr> {
+    T::comptainer(components)
+        .write()
+        .unwrap()
+        .get_mut(entity)
+        .ok_or(ReflectErr::MissingComponent(entity))?
+        .set_path(path, value)
+}
+fn deserialize<T: StoredComponent + Reflect>(
+    value: yml::Value,
+    map: &EntityMap,
+) -> Result<DeserializedComponent, ReflectErr> {
+    let mut comp: T = yml::from_value(value)?;
+    comp.map_entities(map);
+    Ok(DeserializedComponent(Box::new(
+        move |components, entity| {
+            _ = T::comptainer(components)
+                .write()
+                .unwrap()
+                .add_component(entity, comp);
+        },
+    )))
+}
+
+/// Every component type that can be inspected and edited generically
+pub const REFLECTED_COMPONENTS: &[ReflectedComponent] = &[
+    ReflectedComponent::of::<game::Transform>("transform"),
+    ReflectedComponent::of::<render::StaticMeshInstance>("static_mesh_instance"),
+    ReflectedComponent::of::<render::Camera>("camera"),
+    ReflectedComponent::of::<game::ActionHandler>("action_handler"),
+    ReflectedComponent::of::<physics::PhysicsBody>("physics_body"),
+    ReflectedComponent::of::<physics::ColliderShape>("collision_shape"),
+];
+
+pub fn reflected_component(name: &str) -> Result<&'static ReflectedComponent, ReflectErr> {
+    REFLECTED_COMPONENTS
+        .iter()
+        .find(|comp| comp.name == name)
+        .ok_or_else(|| ReflectErr::UnknownComponent(name.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enum_fields_are_listed_by_variant_path() {
+        let names: Vec<_> = reflected_component("collision_shape")
+            .unwrap()
+            .fields
+            .iter()
+            .map(|field| (field.name, field.type_name))
+            .collect();
+        assert_eq!(
+            names,
+            [("Box.dimensions", "Vec3"), ("Sphere.radius", "f32")]
+        );
+
+        let mut shape = physics::ColliderShape::Sphere { radius: 1.0 };
+        let field = &reflected_component("collision_shape").unwrap().fields[1];
+        assert_eq!(shape.get_path(field.name).unwrap(), yml::Value::from(1.0));
+        shape.set_path(field.name, 2.0.into()).unwrap();
+        assert!(matches!(shape, physics::ColliderShape::Sphere { radius } if radius == 2.0));
+        assert!(shape.get_path("Box.dimensions").is_err());
+    }
+
+    #[test]
+    fn skipped_fields_are_not_reflected() {
+        let fields = reflected_component("action_handler").unwrap().fields;
+        assert!(fields.iter().all(|field| field.name != "action_queue"));
+        let handler = game::ActionHandler::new(None);
+        assert!(handler.get_path("action_queue").is_err());
+    }
+}
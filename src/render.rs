@@ -86,7 +86,7 @@ use vulkano::{
 use winit::{raw_window_handle::HandleError, window::Window};
 
 use crate::{
-    framework::{Component, Components, Entity}, geometry::*, input::Input, timer::Timer, System, SystemMessage
+    framework::{ChangeReader, Component, Components, Entity}, geometry::*, input::Input, profile::Profiler, reflect::impl_reflect, timer::Timer, System, SystemMessage
 };
 
 mod shader {
@@ -312,6 +312,7 @@ pub struct RenderInit {
     pub res_x: u16,
     pub res_y: u16,
     pub max_framerate: Option<NonZeroU16>,
+    pub profiler: Arc<RwLock<Profiler>>,
 }
 
 fn new_basic_image(
@@ -409,6 +410,8 @@ pub struct Render {
 
     min_frame_period: Option<Duration>,
 
+    profiler: Arc<RwLock<Profiler>>,
+
     //instance: Arc<Instance>,
     device: Arc<Device>,
     graphics_queue: Arc<Queue>,
@@ -434,6 +437,8 @@ pub struct Render {
 
     components: Arc<Components>,
 
+    mesh_instance_reader: ChangeReader,
+    mesh_instances: AHashMap<Entity, NonZeroU64>,
     mesh_name_to_id: AHashMap<String, NonZeroU64>,
     next_mesh_id: NonZeroU64,
     meshes: AHashMap<NonZeroU64, MeshData>,
@@ -448,6 +453,22 @@ pub struct Render {
     ui_pipeline: Arc<GraphicsPipeline>,
     ui_mats: AHashMap<u64, UIMatData>,
 }
+impl Render {
+    /// Stops drawing the mesh of `entity`, if it had one.
+    fn remove_mesh_instance(&mut self, entity: Entity) {
+        if let Some(mesh_id) = self.mesh_instances.remove(&entity) {
+            if let Some(mesh) = self.meshes.get_mut(&mesh_id) {
+                mesh.entities.retain(|&e| e != entity);
+            }
+        }
+    }
+    fn record_span(&self, name: &'static str, start: Instant) {
+        self.profiler
+            .write()
+            .unwrap()
+            .record("render", name, start, start.elapsed());
+    }
+}
 impl System for Render {
     type Init = RenderInit;
     type InitErr = RenderError;
@@ -461,6 +482,7 @@ impl System for Render {
             res_x,
             res_y,
             max_framerate,
+            profiler,
         }: RenderInit,
         receiver: Receiver<RenderMessage>,
     ) -> Result<Self, RenderError> {
@@ -1103,6 +1125,7 @@ impl System for Render {
             receiver,
             inverse_aspect_ratio: res_y as f32 / res_x as f32,
             min_frame_period: max_framerate.map(|fr| Duration::from_secs_f32((fr.get() as f32).recip())),
+            profiler,
             device,
             graphics_queue,
             transfer_queue,
@@ -1116,6 +1139,8 @@ impl System for Render {
             defer_pipeline,
             cmd_buffer_alloc,
             components: comps.clone(),
+            mesh_instance_reader: comps.static_mesh_instances.write().unwrap().change_reader(),
+            mesh_instances: AHashMap::default(),
             mesh_name_to_id: AHashMap::default(),
             next_mesh_id: NonZeroU64::new(1).unwrap(),
             meshes: AHashMap::new(),
@@ -1148,6 +1173,7 @@ impl System for Render {
                 next_time = now + frame_period;
             }
             log::trace!("frame start");
+            let frame_start = Instant::now();
             ui_updates.clear();
             for msg in self.receiver.try_iter() {
                 match msg {
@@ -1156,200 +1182,197 @@ impl System for Render {
                 }
             }
 
-            for (e, instance) in self
-                .components
-                .static_mesh_instances
-                .write()
-                .unwrap()
-                .iter_mut()
-            {
-                if instance.mesh_id.is_none() {
-                    let id = match self.mesh_name_to_id.entry(instance.mesh_name.clone()) {
-                        Entry::Occupied(occupied) => occupied.get().clone(),
-                        Entry::Vacant(vacant) => {
-                            let mesh_id = self.next_mesh_id;
-                            self.next_mesh_id = self.next_mesh_id.checked_add(1).unwrap();
-                            vacant.insert(mesh_id);
-
-                            let (models, _) = tobj::load_obj(
-                                format!("assets/meshes/{}.obj", instance.mesh_name),
-                                &tobj::GPU_LOAD_OPTIONS,
-                            )?;
-                            assert!(
-                                models.len() == 1,
-                                "Odd number of models in obj file: {}",
-                                models.len()
+            let transfer_start = Instant::now();
+            let components = self.components.clone();
+            let mut mesh_instances = components.static_mesh_instances.write().unwrap();
+            for e in mesh_instances.iter_removed(&self.mesh_instance_reader) {
+                self.remove_mesh_instance(e);
+            }
+            for (e, instance) in mesh_instances.iter_changed(&self.mesh_instance_reader) {
+                self.remove_mesh_instance(e);
+
+                let mesh_id = match self.mesh_name_to_id.entry(instance.mesh_name.clone()) {
+                    Entry::Occupied(occupied) => *occupied.get(),
+                    Entry::Vacant(vacant) => {
+                        let mesh_id = self.next_mesh_id;
+                        self.next_mesh_id = self.next_mesh_id.checked_add(1).unwrap();
+                        vacant.insert(mesh_id);
+
+                        let (models, _) = tobj::load_obj(
+                            format!("assets/meshes/{}.obj", instance.mesh_name),
+                            &tobj::GPU_LOAD_OPTIONS,
+                        )?;
+                        assert!(
+                            models.len() == 1,
+                            "Odd number of models in obj file: {}",
+                            models.len()
+                        );
+
+                        if models[0].name != instance.mesh_name {
+                            log::warn!(
+                                "OBJ Model name \"{}\" differs from mesh name \"{}\"",
+                                &models[0].name,
+                                &instance.mesh_name
                             );
+                        }
 
-                            if models[0].name != instance.mesh_name {
-                                log::warn!(
-                                    "OBJ Model name \"{}\" differs from mesh name \"{}\"",
-                                    &models[0].name,
-                                    &instance.mesh_name
-                                );
-                            }
-
-                            let mesh = &models[0].mesh;
+                        let mesh = &models[0].mesh;
 
-                            let vert_src = Buffer::from_iter(
-                                self.allocator.clone(),
-                                BufferCreateInfo {
-                                    usage: BufferUsage::TRANSFER_SRC,
-                                    ..Default::default()
-                                },
-                                AllocationCreateInfo {
-                                    memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                                    ..Default::default()
-                                },
-                                mesh.positions.chunks(3).zip(mesh.normals.chunks(3)).map(
-                                    |(pos, norm)| {
-                                        VertexData::new(
-                                            Vec3::new(pos[0], pos[1], pos[2]),
-                                            Vec3::new(norm[0], norm[1], norm[2]),
-                                        )
-                                    },
-                                ),
-                            )?;
-                            let vert_dst: Subbuffer<[VertexData]> = Buffer::new_slice(
-                                self.allocator.clone(),
-                                BufferCreateInfo {
-                                    usage: BufferUsage::VERTEX_BUFFER | BufferUsage::TRANSFER_DST,
-                                    ..Default::default()
-                                },
-                                AllocationCreateInfo::default(),
-                                mesh.positions.len() as u64,
-                            )?;
-
-                            let index_src = Buffer::from_iter(
-                                self.allocator.clone(),
-                                BufferCreateInfo {
-                                    usage: BufferUsage::TRANSFER_SRC,
-                                    ..Default::default()
-                                },
-                                AllocationCreateInfo {
-                                    memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                                    ..Default::default()
-                                },
-                                mesh.indices.iter().map(|&i| i as u16),
-                            )?;
-                            let index_dst: Subbuffer<[u16]> = Buffer::new_slice(
-                                self.allocator.clone(),
-                                BufferCreateInfo {
-                                    usage: BufferUsage::INDEX_BUFFER | BufferUsage::TRANSFER_DST,
-                                    ..Default::default()
+                        let vert_src = Buffer::from_iter(
+                            self.allocator.clone(),
+                            BufferCreateInfo {
+                                usage: BufferUsage::TRANSFER_SRC,
+                                ..Default::default()
+                            },
+                            AllocationCreateInfo {
+                                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                                ..Default::default()
+                            },
+                            mesh.positions.chunks(3).zip(mesh.normals.chunks(3)).map(
+                                |(pos, norm)| {
+                                    VertexData::new(
+                                        Vec3::new(pos[0], pos[1], pos[2]),
+                                        Vec3::new(norm[0], norm[1], norm[2]),
+                                    )
                                 },
-                                AllocationCreateInfo::default(),
-                                mesh.indices.len() as u64,
-                            )?;
-
-                            let mut cb_builder = AutoCommandBufferBuilder::primary(
-                                self.cmd_buffer_alloc.clone(),
-                                self.transfer_queue.queue_family_index(),
-                                CommandBufferUsage::OneTimeSubmit,
-                            )?;
-                            cb_builder
-                                .copy_buffer(CopyBufferInfoTyped::buffers(
-                                    vert_src,
-                                    vert_dst.clone(),
-                                ))?
-                                .copy_buffer(CopyBufferInfoTyped::buffers(
-                                    index_src,
-                                    index_dst.clone(),
-                                ))?;
+                            ),
+                        )?;
+                        let vert_dst: Subbuffer<[VertexData]> = Buffer::new_slice(
+                            self.allocator.clone(),
+                            BufferCreateInfo {
+                                usage: BufferUsage::VERTEX_BUFFER | BufferUsage::TRANSFER_DST,
+                                ..Default::default()
+                            },
+                            AllocationCreateInfo::default(),
+                            mesh.positions.len() as u64,
+                        )?;
 
-                            let fence = cb_builder
-                                .build()?
-                                .execute(self.transfer_queue.clone())?
-                                .then_signal_fence_and_flush()?;
-
-                            self.meshes.insert(
-                                mesh_id,
-                                MeshData {
-                                    vert_buffer: vert_dst,
-                                    index_buffer: index_dst,
-                                    entities: vec![e],
-                                },
-                            );
+                        let index_src = Buffer::from_iter(
+                            self.allocator.clone(),
+                            BufferCreateInfo {
+                                usage: BufferUsage::TRANSFER_SRC,
+                                ..Default::default()
+                            },
+                            AllocationCreateInfo {
+                                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                                ..Default::default()
+                            },
+                            mesh.indices.iter().map(|&i| i as u16),
+                        )?;
+                        let index_dst: Subbuffer<[u16]> = Buffer::new_slice(
+                            self.allocator.clone(),
+                            BufferCreateInfo {
+                                usage: BufferUsage::INDEX_BUFFER | BufferUsage::TRANSFER_DST,
+                                ..Default::default()
+                            },
+                            AllocationCreateInfo::default(),
+                            mesh.indices.len() as u64,
+                        )?;
 
-                            fence.wait(None)?;
+                        let mut cb_builder = AutoCommandBufferBuilder::primary(
+                            self.cmd_buffer_alloc.clone(),
+                            self.transfer_queue.queue_family_index(),
+                            CommandBufferUsage::OneTimeSubmit,
+                        )?;
+                        cb_builder
+                            .copy_buffer(CopyBufferInfoTyped::buffers(vert_src, vert_dst.clone()))?
+                            .copy_buffer(CopyBufferInfoTyped::buffers(
+                                index_src,
+                                index_dst.clone(),
+                            ))?;
+
+                        let fence = cb_builder
+                            .build()?
+                            .execute(self.transfer_queue.clone())?
+                            .then_signal_fence_and_flush()?;
+
+                        self.meshes.insert(
+                            mesh_id,
+                            MeshData {
+                                vert_buffer: vert_dst,
+                                index_buffer: index_dst,
+                                entities: Vec::new(),
+                            },
+                        );
+
+                        fence.wait(None)?;
+
+                        mesh_id
+                    }
+                };
+                self.meshes.get_mut(&mesh_id).unwrap().entities.push(e);
+                self.mesh_instances.insert(e, mesh_id);
 
-                            mesh_id
-                        }
-                    };
-                    self.meshes.get_mut(&id).unwrap().entities.push(e);
-                    instance.mesh_id = Some(id);
-                }
-                if instance.material_id.is_none() {
-                    let id = match self.mat_name_to_id.entry(instance.material_name.clone()) {
-                        Entry::Occupied(occupied) => occupied.get().clone(),
-                        Entry::Vacant(vacant) => {
-                            let mat_id = self.next_mat_id;
-                            self.next_mat_id = self.next_mat_id.checked_add(1).unwrap();
-                            vacant.insert(mat_id);
-
-                            let material: Material = yml::from_reader(File::open(format!(
-                                "assets/materials/{}.yaml",
-                                &instance.material_name
-                            ))?)?;
-
-                            let src_buf = Buffer::from_data(
-                                self.allocator.clone(),
-                                BufferCreateInfo {
-                                    usage: BufferUsage::TRANSFER_SRC,
-                                    ..Default::default()
-                                },
-                                AllocationCreateInfo {
-                                    memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                                    ..Default::default()
-                                },
-                                material,
-                            )?;
-                            let dst_buf = self.mat_buffer.clone().index(mat_id.get());
-
-                            let mut cb_builder = AutoCommandBufferBuilder::primary(
-                                self.cmd_buffer_alloc.clone(),
-                                self.transfer_queue.queue_family_index(),
-                                CommandBufferUsage::OneTimeSubmit,
-                            )?;
-                            cb_builder
-                                .copy_buffer(CopyBufferInfo::buffers(src_buf, dst_buf.clone()))?;
-
-                            let fence = cb_builder
-                                .build()?
-                                .execute(self.transfer_queue.clone())?
-                                .then_signal_fence_and_flush()?;
-
-                            let desc_set = DescriptorSet::new(
-                                self.desc_set_alloc.clone(),
-                                self.mat_desc_set_layout.clone(),
-                                [WriteDescriptorSet::buffer(0, dst_buf.clone())],
-                                [],
-                            )?;
-
-                            self.materials.insert(
-                                mat_id,
-                                MatData {
-                                    //buffer: dst_buf,
-                                    desc_set,
-                                },
-                            );
+                if let Entry::Vacant(vacant) =
+                    self.mat_name_to_id.entry(instance.material_name.clone())
+                {
+                    let mat_id = self.next_mat_id;
+                    self.next_mat_id = self.next_mat_id.checked_add(1).unwrap();
+                    vacant.insert(mat_id);
+
+                    let material: Material = yml::from_reader(File::open(format!(
+                        "assets/materials/{}.yaml",
+                        &instance.material_name
+                    ))?)?;
+
+                    let src_buf = Buffer::from_data(
+                        self.allocator.clone(),
+                        BufferCreateInfo {
+                            usage: BufferUsage::TRANSFER_SRC,
+                            ..Default::default()
+                        },
+                        AllocationCreateInfo {
+                            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                            ..Default::default()
+                        },
+                        material,
+                    )?;
+                    let dst_buf = self.mat_buffer.clone().index(mat_id.get());
 
-                            fence.wait(None)?;
+                    let mut cb_builder = AutoCommandBufferBuilder::primary(
+                        self.cmd_buffer_alloc.clone(),
+                        self.transfer_queue.queue_family_index(),
+                        CommandBufferUsage::OneTimeSubmit,
+                    )?;
+                    cb_builder.copy_buffer(CopyBufferInfo::buffers(src_buf, dst_buf.clone()))?;
 
-                            mat_id
-                        }
-                    };
-                    instance.material_id = Some(id);
+                    let fence = cb_builder
+                        .build()?
+                        .execute(self.transfer_queue.clone())?
+                        .then_signal_fence_and_flush()?;
+
+                    let desc_set = DescriptorSet::new(
+                        self.desc_set_alloc.clone(),
+                        self.mat_desc_set_layout.clone(),
+                        [WriteDescriptorSet::buffer(0, dst_buf.clone())],
+                        [],
+                    )?;
+
+                    self.materials.insert(
+                        mat_id,
+                        MatData {
+                            //buffer: dst_buf,
+                            desc_set,
+                        },
+                    );
+
+                    fence.wait(None)?;
                 }
             }
+            mesh_instances.update_reader(&mut self.mesh_instance_reader);
+            drop(mesh_instances);
+            self.record_span("Asset transfer", transfer_start);
 
+            let wait_start = Instant::now();
             if let Some(fence) = prev_fence.take() {
                 fence.wait(None)?;
             }
+            self.record_span("GPU wait", wait_start);
 
+            let ui_start = Instant::now();
             let ui_fence = if !ui_updates.is_empty() {
                 let mut cb_builder = AutoCommandBufferBuilder::primary(
                     self.cmd_buffer_alloc.clone(),
@@ -1590,6 +1613,7 @@ impl System for Render {
             } else {
                 None
             };
+            self.record_span("UI transfer", ui_start);
 
             let (img_index, suboptimal, acquire_future) =
                 match acquire_next_image(self.swapchain.clone(), None) {
@@ -1638,18 +1662,12 @@ impl System for Render {
                 .unwrap()
                 .get_one()
                 .map(|(e, c)| (e, *c));
-            if let Some((e, cam)) = maybe_cam_data {
-                let cam_transform = self
-                    .components
-                    .transforms
-                    .read()
-                    .unwrap()
-                    .get(e)
-                    .unwrap()
-                    .clone();
-                let inv_cam = cam_transform
-                    .global_motor(&self.components.transforms.read().unwrap())
-                    .inverse();
+            let maybe_cam_data = maybe_cam_data.and_then(|(e, cam)| {
+                let global_transforms = self.components.global_transforms.read().unwrap();
+                Some((global_transforms.get(e)?.motor, cam))
+            });
+            if let Some((cam_motor, cam)) = maybe_cam_data {
+                let inv_cam = cam_motor.inverse();
                 let proj_factor = 1.0 / (cam.fov * 0.5).tan();
 
                 builder
@@ -1668,7 +1686,7 @@ impl System for Render {
                         },
                     )?;
                 {
-                    let transforms = self.components.transforms.read().unwrap();
+                    let global_transforms = self.components.global_transforms.read().unwrap();
                     for (_, mat_data) in &self.materials {
                         builder.bind_descriptor_sets(
                             PipelineBindPoint::Graphics,
@@ -1681,15 +1699,14 @@ impl System for Render {
                                 .bind_vertex_buffers(0, mesh.vert_buffer.clone())?
                                 .bind_index_buffer(mesh.index_buffer.clone())?;
                             for &e in &mesh.entities {
+                                // Not propagated yet if the transform was just added
+                                let Some(global) = global_transforms.get(e) else {
+                                    continue;
+                                };
                                 builder.push_constants(
                                     self.fore_pipeline.layout().clone(),
                                     offset_of!(PushData, obj) as u32,
-                                    transforms
-                                        .get(e)
-                                        .expect(&format!(
-                                            "Expected tranform of entity {e} in render"
-                                        ))
-                                        .global_motor(&transforms),
+                                    global.motor,
                                 )?;
                                 unsafe {
                                     builder.draw_indexed(
@@ -1783,6 +1800,7 @@ impl System for Render {
                     };
                 }
             }
+            self.record_span("Frame", frame_start);
         }
     }
 }
@@ -1791,21 +1809,19 @@ impl System for Render {
 pub struct StaticMeshInstance {
     pub mesh_name: String,
     pub material_name: String,
-    #[serde(skip)]
-    mesh_id: Option<NonZeroU64>,
-    #[serde(skip)]
-    material_id: Option<NonZeroU64>,
 }
 impl StaticMeshInstance {
     pub fn new(mesh_name: String, material_name: String) -> Self {
         Self {
             mesh_name,
             material_name,
-            mesh_id: None,
-            material_id: None,
         }
     }
 }
+impl_reflect!(StaticMeshInstance {
+    mesh_name: String,
+    material_name: String,
+});
 impl Component for StaticMeshInstance {}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -1813,6 +1829,10 @@ pub struct Camera {
     pub fov: f32,
     pub near_plane: f32,
 }
+impl_reflect!(Camera {
+    fov: f32,
+    near_plane: f32,
+});
 impl Camera {
     pub fn new(fov: f32, near_plane: f32) -> Self {
         Self { fov, near_plane }
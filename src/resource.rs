@@ -0,0 +1,78 @@
+use std::{
+    any::{Any, TypeId},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use ahash::AHashMap;
+
+/// Type-keyed store of global singletons, such as [`Input`](crate::input::Input), available to every process.
+///
+/// Each resource has its own lock, so processes only contend on the resources they actually use.
+#[derive(Debug, Default)]
+pub struct Resources {
+    /// Each value is an `Arc<RwLock<T>>`, keyed by the `TypeId` of `T`
+    map: AHashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    step_hooks: Vec<fn(&Resources)>,
+}
+impl Resources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds `value` as the resource of type `T`, returning the lock of the resource it replaced if there was one.
+    pub fn insert_resource<T: Send + Sync + 'static>(
+        &mut self,
+        value: T,
+    ) -> Option<Arc<RwLock<T>>> {
+        self.insert_shared(Arc::new(RwLock::new(value)))
+    }
+    /// Adds a resource that is also used outside of the game system, ex. by the renderer.
+    pub fn insert_shared<T: Send + Sync + 'static>(
+        &mut self,
+        value: Arc<RwLock<T>>,
+    ) -> Option<Arc<RwLock<T>>> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast::<Arc<RwLock<T>>>().unwrap())
+    }
+    pub fn remove_resource<T: Send + Sync + 'static>(&mut self) -> Option<Arc<RwLock<T>>> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .map(|old| *old.downcast::<Arc<RwLock<T>>>().unwrap())
+    }
+    pub fn contains_resource<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+    fn lock<T: Send + Sync + 'static>(&self) -> Option<&RwLock<T>> {
+        Some(
+            self.map
+                .get(&TypeId::of::<T>())?
+                .downcast_ref::<Arc<RwLock<T>>>()
+                .unwrap(),
+        )
+    }
+    /// Returns the lock of the resource, for holding onto it outside of a single step.
+    pub fn shared<T: Send + Sync + 'static>(&self) -> Option<Arc<RwLock<T>>> {
+        Some(
+            self.map
+                .get(&TypeId::of::<T>())?
+                .downcast_ref::<Arc<RwLock<T>>>()
+                .unwrap()
+                .clone(),
+        )
+    }
+    pub fn resource<T: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'_, T>> {
+        Some(self.lock::<T>()?.read().unwrap())
+    }
+    pub fn resource_mut<T: Send + Sync + 'static>(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        Some(self.lock::<T>()?.write().unwrap())
+    }
+    /// Adds a function to run at the end of every game step, ex. to clear out old events.
+    pub fn add_step_hook(&mut self, hook: fn(&Resources)) {
+        self.step_hooks.push(hook);
+    }
+    pub fn run_step_hooks(&self) {
+        for hook in &self.step_hooks {
+            hook(self);
+        }
+    }
+}
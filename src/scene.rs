@@ -0,0 +1,292 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::RwLock,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yml as yml;
+
+use hydrolox_pga3d::prelude as pga;
+
+use crate::{
+    framework::{Component, Components, Comptainer, Entity, EntityMap},
+    game, physics,
+    prefab::{Prefab, PrefabErr, PrefabOverrides},
+    render,
+};
+
+#[derive(Debug)]
+pub enum SceneErr {
+    Yaml(yml::Error),
+    Binary(bincode::Error),
+    IO(std::io::Error),
+    Prefab(PrefabErr),
+}
+impl Display for SceneErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Yaml(err) => writeln!(f, "Yaml error: {err}"),
+            Self::Binary(err) => writeln!(f, "Binary encoding error: {err}"),
+            Self::IO(err) => writeln!(f, "IO error: {err}"),
+            Self::Prefab(err) => writeln!(f, "Failed to instantiate scene prefab: {err}"),
+        }
+    }
+}
+impl Error for SceneErr {}
+impl From<yml::Error> for SceneErr {
+    fn from(value: yml::Error) -> Self {
+        Self::Yaml(value)
+    }
+}
+impl From<bincode::Error> for SceneErr {
+    fn from(value: bincode::Error) -> Self {
+        Self::Binary(value)
+    }
+}
+impl From<std::io::Error> for SceneErr {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+impl From<PrefabErr> for SceneErr {
+    fn from(value: PrefabErr) -> Self {
+        Self::Prefab(value)
+    }
+}
+
+fn scene_path(name: &str) -> String {
+    format!("assets/scenes/{name}.yaml")
+}
+
+/// A prefab placed in a scene, for scenes written by hand. Saved scenes store every component
+/// instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScenePrefab {
+    prefab: String,
+    #[serde(default)]
+    position: [f32; 3],
+    /// Euler angles in radians
+    #[serde(default)]
+    rotation: [f32; 3],
+}
+
+/// Borrowed view of the world's serializable comptainers, used when saving.
+#[derive(Debug, Serialize)]
+struct SceneRef<'a> {
+    prefabs: &'a [ScenePrefab],
+    transforms: &'a Comptainer<game::Transform>,
+    static_mesh_instances: &'a Comptainer<render::StaticMeshInstance>,
+    cameras: &'a Comptainer<render::Camera>,
+    action_handlers: &'a Comptainer<game::ActionHandler>,
+    physics_bodies: &'a Comptainer<physics::PhysicsBody>,
+    collision_shapes: &'a Comptainer<physics::ColliderShape>,
+}
+
+/// Owned scene contents with the entity ids they were saved with, used when loading.
+#[derive(Debug, Deserialize)]
+struct SceneData {
+    #[serde(default)]
+    prefabs: Vec<ScenePrefab>,
+    #[serde(default)]
+    transforms: Comptainer<game::Transform>,
+    #[serde(default)]
+    static_mesh_instances: Comptainer<render::StaticMeshInstance>,
+    #[serde(default)]
+    cameras: Comptainer<render::Camera>,
+    #[serde(default)]
+    action_handlers: Comptainer<game::ActionHandler>,
+    #[serde(default)]
+    physics_bodies: Comptainer<physics::PhysicsBody>,
+    #[serde(default)]
+    collision_shapes: Comptainer<physics::ColliderShape>,
+}
+impl SceneData {
+    fn entity_map(&self) -> EntityMap {
+        let mut map = EntityMap::new();
+        let entities = self
+            .transforms
+            .iter()
+            .map(|(e, _)| e)
+            .chain(self.static_mesh_instances.iter().map(|(e, _)| e))
+            .chain(self.cameras.iter().map(|(e, _)| e))
+            .chain(self.action_handlers.iter().map(|(e, _)| e))
+            .chain(self.physics_bodies.iter().map(|(e, _)| e))
+            .chain(self.collision_shapes.iter().map(|(e, _)| e));
+        for e in entities {
+            map.get_or_insert(e);
+        }
+        map
+    }
+    /// Adds the scene to `components`, giving every entity a fresh id. Returns the new entities.
+    fn spawn(mut self, components: &Components) -> Result<Vec<Entity>, SceneErr> {
+        let map = self.entity_map();
+
+        fn merge<T: Component>(
+            from: &mut Comptainer<T>,
+            into: &RwLock<Comptainer<T>>,
+            map: &EntityMap,
+        ) {
+            let mut into = into.write().unwrap();
+            for (e, mut comp) in from.take_iter() {
+                comp.map_entities(map);
+                _ = into.add_component(map.map(e), comp);
+            }
+        }
+
+        merge(&mut self.transforms, &components.transforms, &map);
+        merge(
+            &mut self.static_mesh_instances,
+            &components.static_mesh_instances,
+            &map,
+        );
+        merge(&mut self.cameras, &components.cameras, &map);
+        merge(&mut self.action_handlers, &components.action_handlers, &map);
+        merge(&mut self.physics_bodies, &components.physics_bodies, &map);
+        merge(
+            &mut self.collision_shapes,
+            &components.collision_shapes,
+            &map,
+        );
+
+        let mut entities: Vec<Entity> = map.entities().collect();
+        for ScenePrefab {
+            prefab,
+            position: [x, y, z],
+            rotation: [rx, ry, rz],
+        } in &self.prefabs
+        {
+            entities.extend(
+                Prefab::load(prefab)?.instantiate(
+                    components,
+                    pga::Motor::from_euler_angles(*rx, *ry, *rz)
+                        .combine(pga::Motor::from_translation(*x, *y, *z)),
+                    &PrefabOverrides::new(),
+                )?,
+            );
+        }
+        Ok(entities)
+    }
+}
+
+impl Components {
+    /// Calls `func` with a view of the world's serializable comptainers. UI components hold
+    /// arbitrary elements that can't be serialized, so they are left out with a warning. Global
+    /// transforms and children are recomputed from the transforms after loading.
+    fn with_scene_ref<R>(&self, func: impl FnOnce(&SceneRef) -> R) -> R {
+        for (e, _) in self.uis.read().unwrap().iter() {
+            log::warn!("Entity {e} has a UI component, which can't be saved; skipping it");
+        }
+
+        let transforms = self.transforms.read().unwrap();
+        let static_mesh_instances = self.static_mesh_instances.read().unwrap();
+        let cameras = self.cameras.read().unwrap();
+        let action_handlers = self.action_handlers.read().unwrap();
+        let physics_bodies = self.physics_bodies.read().unwrap();
+        let collision_shapes = self.collision_shapes.read().unwrap();
+
+        func(&SceneRef {
+            prefabs: &[],
+            transforms: &transforms,
+            static_mesh_instances: &static_mesh_instances,
+            cameras: &cameras,
+            action_handlers: &action_handlers,
+            physics_bodies: &physics_bodies,
+            collision_shapes: &collision_shapes,
+        })
+    }
+    /// Saves the world's serializable components to `assets/scenes/<name>.yaml`.
+    pub fn save_scene(&self, name: &str) -> Result<(), SceneErr> {
+        std::fs::create_dir_all("assets/scenes")?;
+        let file = BufWriter::new(File::create(scene_path(name))?);
+        self.with_scene_ref(|scene| yml::to_writer(file, scene))?;
+        Ok(())
+    }
+    /// Loads `assets/scenes/<name>.yaml` into the world, remapping its entities to fresh ids.
+    /// Returns the entities that were spawned.
+    pub fn load_scene(&self, name: &str) -> Result<Vec<Entity>, SceneErr> {
+        let file = BufReader::new(File::open(scene_path(name))?);
+        let scene: SceneData = yml::from_reader(file)?;
+        scene.spawn(self)
+    }
+    /// Saves the world's serializable components to `path` using a compact binary encoding, for
+    /// save games.
+    pub fn save_game<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneErr> {
+        let file = BufWriter::new(File::create(path)?);
+        self.with_scene_ref(|scene| bincode::serialize_into(file, scene))?;
+        Ok(())
+    }
+    /// Loads a save game written by [`Components::save_game`], remapping its entities to fresh ids.
+    /// Returns the entities that were spawned.
+    pub fn load_game<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Entity>, SceneErr> {
+        let file = BufReader::new(File::open(path)?);
+        let scene: SceneData = bincode::deserialize_from(file)?;
+        scene.spawn(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_scenes_keep_every_serializable_component() {
+        let components = Components::default();
+        let world = Entity::new();
+        let player = Entity::new();
+        for e in [world, player] {
+            components
+                .transforms
+                .write()
+                .unwrap()
+                .add_component(e, game::Transform::new(None, pga::Motor::IDENTITY));
+        }
+        components
+            .static_mesh_instances
+            .write()
+            .unwrap()
+            .add_component(
+                world,
+                render::StaticMeshInstance::new("monkey".into(), "slate_gray".into()),
+            );
+        components.cameras.write().unwrap().add_component(
+            player,
+            render::Camera {
+                fov: 45.0,
+                near_plane: 0.1,
+            },
+        );
+        components
+            .action_handlers
+            .write()
+            .unwrap()
+            .add_component(player, game::ActionHandler::new(None));
+
+        let yaml = components
+            .with_scene_ref(|scene| yml::to_string(scene))
+            .unwrap();
+        let scene: SceneData = yml::from_str(&yaml).unwrap();
+        let loaded = Components::default();
+        let entities = scene.spawn(&loaded).unwrap();
+
+        assert_eq!(entities.len(), 2);
+        assert_eq!(loaded.transforms.read().unwrap().len(), 2);
+        assert_eq!(loaded.static_mesh_instances.read().unwrap().len(), 1);
+        assert_eq!(loaded.cameras.read().unwrap().len(), 1);
+        assert_eq!(loaded.action_handlers.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn startup_scene_parses() {
+        let file = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/scenes/startup.yaml"
+        ))
+        .unwrap();
+        let scene: SceneData = yml::from_reader(BufReader::new(file)).unwrap();
+        assert!(!scene.prefabs.is_empty());
+    }
+}
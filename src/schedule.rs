@@ -0,0 +1,364 @@
+use std::{error::Error, fmt::Display, sync::RwLock, time::Instant};
+
+use rayon::{prelude::*, ThreadPool};
+
+use crate::{
+    command::Commands,
+    framework::Components,
+    game::{Process, ProcessStage},
+    profile::Profiler,
+    resource::Resources,
+    timer::Stopwatch,
+};
+
+#[derive(Debug)]
+pub enum ScheduleErr {
+    DuplicateName(&'static str),
+    /// A process must run before another process in an earlier stage
+    StageOrder {
+        first: &'static str,
+        then: &'static str,
+    },
+    Cycle(Vec<&'static str>),
+}
+impl Display for ScheduleErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateName(name) => writeln!(f, "Multiple processes named \"{name}\""),
+            Self::StageOrder { first, then } => writeln!(
+                f,
+                "Process \"{first}\" must run before \"{then}\", but is in a later stage"
+            ),
+            Self::Cycle(names) => writeln!(
+                f,
+                "Process ordering has a cycle: {} -> {}",
+                names.join(" -> "),
+                names[0]
+            ),
+        }
+    }
+}
+impl Error for ScheduleErr {}
+
+#[derive(Debug)]
+struct ScheduledProcess {
+    process: Box<dyn Process>,
+    enabled: bool,
+    commands: Commands,
+}
+
+/// The processes of a single [`ProcessStage`], grouped into batches that can run concurrently.
+///
+/// Batches run in order. Processes in the same batch have no conflicting component or resource
+/// access and no ordering constraints between them.
+#[derive(Debug, Default)]
+struct StageSchedule {
+    batches: Vec<Vec<ScheduledProcess>>,
+}
+impl StageSchedule {
+    /// `processes` must already be sorted, with `edges` holding pairs of indices into it where the
+    /// first must run before the second.
+    fn new(processes: Vec<Box<dyn Process>>, edges: &[(usize, usize)]) -> Self {
+        let mut batches: Vec<Vec<ScheduledProcess>> = Vec::new();
+        let mut last_batch_start = 0;
+        for (i, process) in processes.into_iter().enumerate() {
+            let access = process.access();
+            let fits = batches.last().is_some_and(|batch| {
+                !batch.iter().any(|p| p.process.access().conflicts(&access))
+                    && !edges
+                        .iter()
+                        .any(|&(first, then)| then == i && first >= last_batch_start)
+            });
+            let scheduled = ScheduledProcess {
+                process,
+                enabled: true,
+                commands: Commands::new(),
+            };
+            if fits {
+                batches.last_mut().unwrap().push(scheduled);
+            } else {
+                last_batch_start = i;
+                batches.push(vec![scheduled]);
+            }
+        }
+        Self { batches }
+    }
+    /// Steps every enabled process, then applies their commands in schedule order. Processes in the
+    /// same batch run concurrently on `pool` if there is one, otherwise everything runs sequentially.
+    fn run(
+        &mut self,
+        pool: Option<&ThreadPool>,
+        components: &Components,
+        resources: &mut Resources,
+        profiler: Option<&RwLock<Profiler>>,
+        paused: bool,
+    ) {
+        let shared_resources = &*resources;
+        for batch in &mut self.batches {
+            let step = |scheduled: &mut ScheduledProcess| {
+                if scheduled.enabled && (!paused || scheduled.process.runs_while_paused()) {
+                    let start = Instant::now();
+                    let mut stopwatch = Stopwatch::new();
+                    stopwatch.start();
+                    scheduled
+                        .process
+                        .step(components, shared_resources, &mut scheduled.commands);
+                    if let Some(profiler) = profiler {
+                        profiler.write().unwrap().record(
+                            "process",
+                            scheduled.process.name(),
+                            start,
+                            stopwatch.total(),
+                        );
+                    }
+                }
+            };
+            match pool {
+                Some(pool) if batch.len() > 1 => {
+                    pool.install(|| batch.par_iter_mut().for_each(step))
+                }
+                _ => batch.iter_mut().for_each(step),
+            }
+        }
+
+        for scheduled in self.batches.iter_mut().flatten() {
+            scheduled.commands.apply(components, resources);
+        }
+    }
+}
+
+/// Every process, sorted by stage and by their before/after constraints.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    stages: Vec<StageSchedule>,
+}
+impl Schedule {
+    pub fn new(processes: Vec<Box<dyn Process>>) -> Result<Self, ScheduleErr> {
+        for (i, process) in processes.iter().enumerate() {
+            if processes[..i].iter().any(|p| p.name() == process.name()) {
+                return Err(ScheduleErr::DuplicateName(process.name()));
+            }
+        }
+
+        let stage_index = |process: &dyn Process| {
+            ProcessStage::ALL
+                .iter()
+                .position(|&s| s == process.stage())
+                .unwrap()
+        };
+        let matching = |target: &str| -> Vec<usize> {
+            processes
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.name() == target || p.labels().contains(&target))
+                .map(|(i, _)| i)
+                .collect()
+        };
+
+        // Pairs of indices into `processes`, where the first must run before the second
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for (i, process) in processes.iter().enumerate() {
+            let constraints = process
+                .after()
+                .iter()
+                .map(|target| (target, true))
+                .chain(process.before().iter().map(|target| (target, false)));
+            for (target, is_after) in constraints {
+                let targets = matching(target);
+                if targets.is_empty() {
+                    log::warn!(
+                        "Process \"{}\" is ordered relative to unknown process or label \"{target}\"",
+                        process.name()
+                    );
+                }
+                for j in targets {
+                    let (first, then) = if is_after { (j, i) } else { (i, j) };
+                    let (first_stage, then_stage) = (
+                        stage_index(processes[first].as_ref()),
+                        stage_index(processes[then].as_ref()),
+                    );
+                    if first_stage > then_stage {
+                        return Err(ScheduleErr::StageOrder {
+                            first: processes[first].name(),
+                            then: processes[then].name(),
+                        });
+                    } else if first_stage == then_stage && first != then {
+                        edges.push((first, then));
+                    }
+                }
+            }
+        }
+
+        let mut remaining: Vec<Option<Box<dyn Process>>> =
+            processes.into_iter().map(Some).collect();
+        let mut stages = Vec::with_capacity(ProcessStage::ALL.len());
+        for stage in ProcessStage::ALL {
+            // Processes in earlier stages have already been taken
+            let in_stage: Vec<usize> = (0..remaining.len())
+                .filter(|&i| remaining[i].as_ref().is_some_and(|p| p.stage() == stage))
+                .collect();
+            let stage_edges: Vec<(usize, usize)> = edges
+                .iter()
+                .copied()
+                .filter(|(first, _)| in_stage.contains(first))
+                .collect();
+
+            // Kahn's algorithm, always picking the earliest added process that is ready so the
+            // order stays deterministic and matches insertion order where unconstrained
+            let mut sorted: Vec<usize> = Vec::with_capacity(in_stage.len());
+            let mut unsorted = in_stage.clone();
+            while let Some(pos) = unsorted.iter().position(|&i| {
+                !stage_edges
+                    .iter()
+                    .any(|&(first, then)| then == i && unsorted.contains(&first))
+            }) {
+                sorted.push(unsorted.remove(pos));
+            }
+            if !unsorted.is_empty() {
+                return Err(ScheduleErr::Cycle(Self::find_cycle(
+                    &unsorted,
+                    &stage_edges,
+                    &remaining,
+                )));
+            }
+
+            let sorted_edges: Vec<(usize, usize)> = stage_edges
+                .iter()
+                .map(|(first, then)| {
+                    (
+                        sorted.iter().position(|i| i == first).unwrap(),
+                        sorted.iter().position(|i| i == then).unwrap(),
+                    )
+                })
+                .collect();
+            let stage_processes = sorted
+                .iter()
+                .map(|&i| remaining[i].take().unwrap())
+                .collect();
+            stages.push(StageSchedule::new(stage_processes, &sorted_edges));
+        }
+
+        Ok(Self { stages })
+    }
+    /// Follows constraints backwards from one of the processes that couldn't be sorted until it
+    /// loops, returning the names of the processes in that loop.
+    fn find_cycle(
+        unsorted: &[usize],
+        edges: &[(usize, usize)],
+        processes: &[Option<Box<dyn Process>>],
+    ) -> Vec<&'static str> {
+        let mut path = vec![unsorted[0]];
+        loop {
+            let current = *path.last().unwrap();
+            // Every unsorted process has an unsorted predecessor, otherwise it would be sorted
+            let (prev, _) = *edges
+                .iter()
+                .find(|&&(first, then)| then == current && unsorted.contains(&first))
+                .unwrap();
+            if let Some(start) = path.iter().position(|&i| i == prev) {
+                let mut cycle: Vec<&'static str> = path[start..]
+                    .iter()
+                    .map(|&i| processes[i].as_ref().unwrap().name())
+                    .collect();
+                cycle.reverse();
+                return cycle;
+            }
+            path.push(prev);
+        }
+    }
+    /// Steps every process once. While `paused`, only processes that run while paused are stepped.
+    pub fn run(
+        &mut self,
+        pool: Option<&ThreadPool>,
+        components: &Components,
+        resources: &mut Resources,
+        paused: bool,
+    ) {
+        let profiler = resources.shared::<Profiler>();
+        for (stage, schedule) in ProcessStage::ALL.iter().zip(&mut self.stages) {
+            let start = Instant::now();
+            let mut stopwatch = Stopwatch::new();
+            stopwatch.start();
+            schedule.run(pool, components, resources, profiler.as_deref(), paused);
+            if let Some(profiler) = &profiler {
+                profiler
+                    .write()
+                    .unwrap()
+                    .record("stage", stage.name(), start, stopwatch.total());
+            }
+        }
+    }
+    /// Enables or disables the process called `name`. Returns false if there is no such process.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        for stage in &mut self.stages {
+            for scheduled in stage.batches.iter_mut().flatten() {
+                if scheduled.process.name() == name {
+                    scheduled.enabled = enabled;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+    use crate::{game::ComponentAccess, render::RenderMessage};
+
+    #[derive(Debug)]
+    struct Staged(&'static str, ProcessStage);
+    impl Process for Staged {
+        fn new(_: &Components, _: &mut Resources, _: &Sender<RenderMessage>) -> Self {
+            Self("Staged", ProcessStage::default())
+        }
+        fn step(&mut self, _: &Components, _: &Resources, _: &mut Commands) {}
+        fn name(&self) -> &'static str {
+            self.0
+        }
+        fn stage(&self) -> ProcessStage {
+            self.1
+        }
+        fn access(&self) -> ComponentAccess {
+            ComponentAccess::ALL
+        }
+    }
+
+    fn names(schedule: &Schedule) -> Vec<Vec<&'static str>> {
+        schedule
+            .stages
+            .iter()
+            .map(|stage| {
+                stage
+                    .batches
+                    .iter()
+                    .flatten()
+                    .map(|scheduled| scheduled.process.name())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn processes_are_split_into_stages() {
+        let processes: Vec<Box<dyn Process>> = vec![
+            Box::new(Staged("Transform", ProcessStage::Late)),
+            Box::new(Staged("Player", ProcessStage::Normal)),
+            Box::new(Staged("Timer", ProcessStage::Early)),
+            Box::new(Staged("UI", ProcessStage::Normal)),
+        ];
+        let schedule = Schedule::new(processes).unwrap();
+        assert_eq!(
+            names(&schedule),
+            vec![
+                vec!["Timer"],
+                vec!["Player", "UI"],
+                vec![],
+                vec!["Transform"]
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    action::{Action, ActionKind, ActionValue},
+    command::Commands,
+    context::SETTINGS,
+    framework::Components,
+    game::UIElement,
+    input::{BindType, Conflict, Input, RebindTarget},
+    resource::Resources,
+};
+
+/// Settings window for viewing and rebinding inputs. Changes are saved to `binds.yaml` as they
+/// are made. Toggled by its handler's action, `ToggleSettings` by default.
+#[derive(Debug)]
+pub struct SettingsPanel {
+    input: Arc<RwLock<Input>>,
+    open: bool,
+    /// Error from the last save, shown until the next one succeeds
+    save_error: Option<String>,
+}
+impl SettingsPanel {
+    pub fn new(resources: &Resources) -> Self {
+        Self {
+            input: resources.shared().unwrap(),
+            open: false,
+            save_error: None,
+        }
+    }
+
+    fn save(&mut self, input: &mut Input) {
+        self.save_error = match input.save_bindings() {
+            Ok(()) => None,
+            Err(err) => {
+                log::error!("Failed to save bindings: {err}");
+                Some(err.to_string().trim_end().into())
+            }
+        };
+    }
+
+    fn button_bindings(ui: &mut egui::Ui, input: &mut Input) -> bool {
+        let mut changed = false;
+        let actions: Vec<String> = input
+            .actions()
+            .filter(|(_, def)| def.kind == ActionKind::Button)
+            .map(|(_, def)| def.name.clone())
+            .collect();
+        let bindings: Vec<_> = input
+            .button_bindings()
+            .into_iter()
+            .map(|(bind, name)| (bind.clone(), name.to_string()))
+            .collect();
+        // The action that takes priority over each binding, for inputs bound more than once
+        let conflicts = input.conflicts();
+        let overridden_by = |bind: &BindType, action: &str| {
+            conflicts.iter().find_map(|conflict| match conflict {
+                Conflict::Duplicate { bind: b, actions } if b == bind && actions[0] != action => {
+                    Some(actions[0].clone())
+                }
+                _ => None,
+            })
+        };
+        egui::Grid::new("button bindings")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for action in &actions {
+                    ui.label(action);
+                    ui.horizontal_wrapped(|ui| {
+                        for (bind, _) in bindings.iter().filter(|(_, name)| name == action) {
+                            let mut text = egui::RichText::new(format!("{bind} ✖"));
+                            let mut hover = "Remove binding".to_string();
+                            if let Some(other) = overridden_by(bind, action) {
+                                text = text.color(ui.visuals().warn_fg_color);
+                                hover = format!("Overridden by {other}. {hover}");
+                            }
+                            if ui.button(text).on_hover_text(hover).clicked() {
+                                input.unbind_button(bind, action);
+                                changed = true;
+                            }
+                        }
+                        let target = RebindTarget::Button(action.clone());
+                        if input.rebind_target() == Some(&target) {
+                            ui.label("…");
+                        } else if ui.button("+").on_hover_text("Add binding").clicked() {
+                            input.start_rebind(target);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        changed
+    }
+
+    fn axis_bindings(ui: &mut egui::Ui, input: &mut Input) -> bool {
+        let mut changed = false;
+        let mut remove = None;
+        for (i, original) in input.axis_bindings().to_vec().into_iter().enumerate() {
+            let mut binding = original.clone();
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(&binding.action);
+                    ui.label(binding.source.to_string());
+                    if ui.button("✖").on_hover_text("Remove binding").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    let names = binding.source.button_names();
+                    for (slot, bind) in binding.source.buttons().into_iter().enumerate() {
+                        let target = RebindTarget::AxisButton { axis: i, slot };
+                        let text = if input.rebind_target() == Some(&target) {
+                            format!("{}: …", names[slot])
+                        } else {
+                            format!("{}: {bind}", names[slot])
+                        };
+                        if ui.button(text).on_hover_text("Rebind").clicked() {
+                            input.start_rebind(target);
+                        }
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut binding.sensitivity)
+                            .speed(0.01)
+                            .prefix("Sensitivity: "),
+                    );
+                    if binding.source.is_normalized() {
+                        ui.add(
+                            egui::DragValue::new(&mut binding.dead_zone)
+                                .speed(0.01)
+                                .range(0.0..=0.95)
+                                .prefix("Dead zone: "),
+                        );
+                    }
+                    ui.checkbox(&mut binding.invert_x, "Invert X");
+                    ui.checkbox(&mut binding.invert_y, "Invert Y");
+                });
+            });
+            ui.separator();
+            if binding != original {
+                input.set_axis_binding(i, binding);
+                changed = true;
+            }
+        }
+        if let Some(i) = remove {
+            input.remove_axis_binding(i);
+            changed = true;
+        }
+        changed
+    }
+}
+impl UIElement for SettingsPanel {
+    fn active(&self) -> bool {
+        self.open
+    }
+    fn handle_action(&mut self, action: Action) {
+        if action.value == ActionValue::Pressed {
+            self.open = !self.open;
+            if !self.open {
+                self.input.write().unwrap().cancel_rebind();
+            }
+        }
+    }
+    fn input_context(&self) -> Option<&'static str> {
+        Some(SETTINGS)
+    }
+    fn show(&mut self, ctx: &egui::Context, _: &Components, _: &mut Commands) {
+        let mut open = self.open;
+        let input_lock = self.input.clone();
+        let mut input = input_lock.write().unwrap();
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.heading("Bindings");
+                    if ui.button("Restore defaults").clicked() {
+                        input.restore_default_bindings();
+                        changed = true;
+                    }
+                });
+                if input.rebind_target().is_some() {
+                    ui.label("Press a key or button to bind it, or left click to cancel");
+                }
+                if let Some(err) = &self.save_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                for conflict in input.conflicts() {
+                    ui.colored_label(ui.visuals().warn_fg_color, conflict.to_string());
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    changed |= Self::button_bindings(ui, &mut input);
+                    ui.separator();
+                    changed |= Self::axis_bindings(ui, &mut input);
+                });
+                if changed {
+                    self.save(&mut input);
+                }
+            });
+        if !open {
+            input.cancel_rebind();
+        }
+        self.open = open;
+    }
+}
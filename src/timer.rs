@@ -1,33 +1,56 @@
-use std::time::{Duration, Instant};
+use std::{
+    ops::Add,
+    time::{Duration, Instant},
+};
 
+/// A point in simulated time, measured from the start of the game. Stops advancing while gameplay
+/// is paused. See [`GameTime`](crate::clock::GameTime).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SimInstant(pub Duration);
+impl Add<Duration> for SimInstant {
+    type Output = Self;
+    fn add(self, rhs: Duration) -> Self {
+        Self(self.0 + rhs)
+    }
+}
+
+/// Counts down `duration` from a point in time, either real ([`Instant`]) or simulated ([`SimInstant`]).
 #[derive(Debug, Clone)]
-pub struct Timer {
-    start: Instant,
+pub struct Timer<T = Instant> {
+    start: T,
     duration: Duration,
 }
 impl Timer {
     pub fn from_now(duration: Duration) -> Self {
-        Self {
-            start: Instant::now(),
-            duration,
-        }
-    }
-    pub fn end(&self) -> Instant {
-        self.start + self.duration
+        Self::starting_at(Instant::now(), duration)
     }
     /// Returns true if the timer's duration has passed, and returns false otherwise.
     pub fn check(&self) -> bool {
-        let now = Instant::now();
-        if now >= self.end() {
-            true
-        } else {
-            false
-        }
+        self.check_at(Instant::now())
     }
     /// Returns true and resets the timer if its duration has passed, and returns false otherwise.
     pub fn check_reset(&mut self) -> bool {
-        let now = Instant::now();
-        if now >= self.end() {
+        self.check_reset_at(Instant::now())
+    }
+}
+impl<T: Copy + Ord + Add<Duration, Output = T>> Timer<T> {
+    pub fn starting_at(start: T, duration: Duration) -> Self {
+        Self { start, duration }
+    }
+    pub fn end(&self) -> T {
+        self.start + self.duration
+    }
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    /// Returns true if the timer's duration has passed by `now`, and returns false otherwise.
+    pub fn check_at(&self, now: T) -> bool {
+        now >= self.end()
+    }
+    /// Returns true and resets the timer to start at `now` if its duration has passed, and returns
+    /// false otherwise.
+    pub fn check_reset_at(&mut self, now: T) -> bool {
+        if self.check_at(now) {
             self.start = now;
             true
         } else {
@@ -36,12 +59,26 @@ impl Timer {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Stopwatch {
     total: Duration,
     last_start: Option<Instant>,
 }
 impl Stopwatch {
+    /// Creates a stopped stopwatch with no time on it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn start(&mut self) {
+        if self.last_start.is_none() {
+            self.last_start = Some(Instant::now());
+        }
+    }
+    /// Clears the total and starts timing from now.
+    pub fn restart(&mut self) {
+        self.total = Duration::new(0, 0);
+        self.last_start = Some(Instant::now());
+    }
     pub fn since_last_start(&self) -> Option<Duration> {
         Some(Instant::now().duration_since(self.last_start?))
     }